@@ -0,0 +1,124 @@
+use crate::extras::ExtraCountry;
+use crate::models;
+use crate::mods::ModExtractor;
+
+const MAGE_ESTATE: &str = "estate_mages";
+
+// Anbennac keeps most of its mechanics in country variables and flags whose names change between
+// mod versions, so they are sorted by the words in their names: `mage` picks `anb_mage_tower`
+// but not `damage`. Flags and variables of the base game and other mechanics are left out.
+const MAGIC_WORDS: [&str; 4] = ["magic", "mage", "school", "spell"];
+const TOLERANCE_WORDS: [&str; 1] = ["tolerance"];
+const ADMINISTRATION_WORDS: [&str; 2] = ["racial_admin", "racial_pop"];
+const MONSTROUS_WORDS: [&str; 1] = ["monstrous"];
+
+pub struct Anbennac;
+
+// Whether one of `words` is among the underscore separated words of `name`. A word may itself be
+// several words, as in `racial_admin`.
+fn matches(name: &str, words: &[&str]) -> bool {
+    let name = format!("_{}_", name.to_lowercase());
+    return words.iter().any(|w| name.contains(&format!("_{}_", w)));
+}
+
+fn generate_anbennac(extra: Option<&ExtraCountry>, realm: &models::CountryRealm) -> models::AnbennacStats {
+    let mut stats = models::AnbennacStats {
+        mage_estate: realm.estates.iter().find(|e| e.estate == MAGE_ESTATE).cloned(),
+        ..Default::default()
+    };
+    let Some(extra) = extra else { return stats };
+
+    for (name, value) in &extra.variables {
+        let target = if matches(name, &MAGIC_WORDS) {
+            &mut stats.magic
+        } else if matches(name, &TOLERANCE_WORDS) {
+            &mut stats.racial_tolerance
+        } else if matches(name, &ADMINISTRATION_WORDS) {
+            &mut stats.racial_administration
+        } else {
+            continue;
+        };
+        target.insert(name.clone(), *value);
+    }
+
+    let mut flags: Vec<&String> = extra.flags.keys().chain(extra.hidden_flags.keys()).collect();
+    flags.sort();
+    flags.dedup();
+    for flag in flags {
+        if matches(flag, &MONSTROUS_WORDS) {
+            stats.monstrous_flags.push(flag.clone());
+        } else if matches(flag, &MAGIC_WORDS) {
+            stats.magic_flags.push(flag.clone());
+        }
+    }
+    stats.monstrous = !stats.monstrous_flags.is_empty();
+    return stats;
+}
+
+impl ModExtractor for Anbennac {
+    fn applies(&self, mods: &[String]) -> bool {
+        return mods.iter().any(|m| m.to_lowercase().contains("anbennac"));
+    }
+
+    fn extract(&self, extra: Option<&ExtraCountry>, stats: &mut models::CountryStats) {
+        stats.anbennac = Some(generate_anbennac(extra, &stats.realm));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use eu4save::Eu4Date;
+
+    #[test]
+    fn words() {
+        let cases = [
+            ("magic_level", &MAGIC_WORDS[..], true),
+            ("anb_mage_tower", &MAGIC_WORDS, true),
+            ("mage", &MAGIC_WORDS, true),
+            ("image_of_the_king", &MAGIC_WORDS, false),
+            ("damage_dealt", &MAGIC_WORDS, false),
+            ("magical_realm", &MAGIC_WORDS, false),
+            ("elven_tolerance", &TOLERANCE_WORDS, true),
+            ("racial_admin_used", &ADMINISTRATION_WORDS, true),
+            ("racial_administration", &ADMINISTRATION_WORDS, false),
+            ("admin_racial", &ADMINISTRATION_WORDS, false),
+            ("is_monstrous_nation", &MONSTROUS_WORDS, true),
+            ("IS_MONSTROUS_NATION", &MONSTROUS_WORDS, true),
+        ];
+        for (name, words, expected) in cases {
+            assert_eq!(matches(name, words), expected, "{} {:?}", name, words);
+        }
+    }
+
+    #[test]
+    fn anbennac() {
+        let date = Eu4Date::parse("1444.11.11").unwrap();
+        let extra = ExtraCountry {
+            variables: [("magic_level", 3.0), ("elven_tolerance", 20.0), ("racial_admin_used", 2.0), ("damage_taken", 5.0), ("prestige_var", 1.0)]
+                .into_iter().map(|(name, value)| (name.to_string(), value)).collect(),
+            flags: [("is_monstrous_nation", date), ("anb_example_flag", date), ("school_of_illusion", date)]
+                .into_iter().map(|(name, date)| (name.to_string(), date)).collect(),
+            hidden_flags: [("image_painted", date), ("school_of_illusion", date)]
+                .into_iter().map(|(name, date)| (name.to_string(), date)).collect(),
+            ..Default::default()
+        };
+        let realm = models::CountryRealm {
+            estates: vec![models::EstateStats { estate: MAGE_ESTATE.to_string(), loyalty: 55.0, ..Default::default() }],
+            ..Default::default()
+        };
+
+        let stats = generate_anbennac(Some(&extra), &realm);
+        assert_eq!(stats.mage_estate.map(|e| e.loyalty), Some(55.0));
+        assert_eq!(stats.magic.into_iter().collect::<Vec<_>>(), [("magic_level".to_string(), 3.0)]);
+        assert_eq!(stats.racial_tolerance.into_iter().collect::<Vec<_>>(), [("elven_tolerance".to_string(), 20.0)]);
+        assert_eq!(stats.racial_administration.into_iter().collect::<Vec<_>>(), [("racial_admin_used".to_string(), 2.0)]);
+        // Hidden flags count as well, once
+        assert_eq!(stats.magic_flags, ["school_of_illusion"]);
+        assert_eq!(stats.monstrous_flags, ["is_monstrous_nation"]);
+        assert!(stats.monstrous);
+
+        let stats = generate_anbennac(None, &models::CountryRealm::default());
+        assert!(stats.mage_estate.is_none() && stats.magic.is_empty() && !stats.monstrous);
+    }
+}
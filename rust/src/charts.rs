@@ -0,0 +1,275 @@
+// Static SVG charts to post outside the web app: monthly income over time, what each player spent
+// developing and bar charts comparing them on key stats. The SVG is written by hand, so nothing
+// beyond the stats is needed to render it.
+use log::info;
+use std::error::Error;
+use std::fmt::Write as _;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::result::Result;
+
+use crate::models::{CountryStats, Eu4Stats};
+use crate::recap::{escape, get_label};
+
+// Compared on bar charts, when the save has them
+const BAR_METRICS: [&str; 8] = [
+    "country.total_development",
+    "country.income",
+    "country.gp_score",
+    "country.max_manpower",
+    "military.army_force_limit",
+    "military.army_discipline",
+    "military.army_morale",
+    "trade.trade_income",
+];
+
+// Countries charted when there are no players
+const NUM_COUNTRIES: usize = 10;
+
+const WIDTH: f32 = 800.0;
+const HEIGHT: f32 = 450.0;
+const MARGIN_LEFT: f32 = 70.0;
+const MARGIN_RIGHT: f32 = 180.0;
+const MARGIN_TOP: f32 = 50.0;
+const MARGIN_BOTTOM: f32 = 50.0;
+const BAR_HEIGHT: f32 = 24.0;
+const BAR_GAP: f32 = 8.0;
+
+const PALETTE: [&str; 12] = [
+    "#1f77b4", "#ff7f0e", "#2ca02c", "#d62728", "#9467bd", "#8c564b",
+    "#e377c2", "#7f7f7f", "#bcbd22", "#17becf", "#393b79", "#637939",
+];
+const MANA_LABELS: [&str; 3] = ["Admin", "Diplo", "Military"];
+const MANA_COLORS: [&str; 3] = ["#2e8b57", "#4169e1", "#b22222"];
+
+fn get_color(i: usize) -> &'static str {
+    return PALETTE[i % PALETTE.len()];
+}
+
+// 1234.5 -> "1234.5", 12.0 -> "12"
+fn format_value(value: f32) -> String {
+    let rounded = (value * 10.0).round() / 10.0;
+    if rounded.fract() == 0.0 {
+        return format!("{:.0}", rounded);
+    }
+    return format!("{:.1}", rounded);
+}
+
+// A round step (1, 2 or 5 times a power of ten) splitting `range` into about `ticks` parts
+fn nice_step(range: f32, ticks: usize) -> f32 {
+    if range <= 0.0 {
+        return 1.0;
+    }
+    let rough = range / ticks as f32;
+    let magnitude = 10f32.powf(rough.log10().floor());
+    return [1.0, 2.0, 5.0, 10.0].iter().map(|m| m * magnitude).find(|s| *s >= rough).unwrap_or(10.0 * magnitude);
+}
+
+// The range from `min` to `max` widened to whole steps, and the steps in it
+fn get_ticks(min: f32, max: f32, ticks: usize) -> (f32, f32, Vec<f32>) {
+    let step = nice_step(max - min, ticks);
+    let start = (min / step).floor() * step;
+    let end = ((max / step).ceil() * step).max(start + step);
+    let count = ((end - start) / step).round() as usize;
+    return (start, end, (0..=count).map(|i| start + i as f32 * step).collect());
+}
+
+fn svg_start(width: f32, height: f32, title: &str) -> String {
+    let mut svg = String::new();
+    let _ = writeln!(svg, r#"<svg xmlns="http://www.w3.org/2000/svg" width="{}" height="{}" viewBox="0 0 {} {}" font-family="sans-serif" font-size="12">"#, width, height, width, height);
+    let _ = writeln!(svg, r#"<rect width="100%" height="100%" fill="white"/>"#);
+    let _ = writeln!(svg, r#"<text x="{}" y="28" text-anchor="middle" font-size="18">{}</text>"#, width / 2.0, escape(title));
+    return svg;
+}
+
+fn legend(svg: &mut String, x: f32, y: f32, entries: &[(String, &str)]) {
+    for (i, (label, color)) in entries.iter().enumerate() {
+        let top = y + i as f32 * 20.0;
+        let _ = writeln!(svg, r#"<rect x="{}" y="{}" width="12" height="12" fill="{}"/>"#, x, top, color);
+        let _ = writeln!(svg, r#"<text x="{}" y="{}">{}</text>"#, x + 18.0, top + 11.0, escape(label));
+    }
+}
+
+// One line per series of (x, y) points
+pub fn line_chart(title: &str, y_label: &str, series: &[(String, Vec<(f32, f32)>)]) -> String {
+    let points = series.iter().flat_map(|(_name, points)| points);
+    let (x_min, x_max, y_min, y_max) = points.fold((f32::MAX, f32::MIN, 0.0f32, f32::MIN), |(x0, x1, y0, y1), (x, y)| {
+        (x0.min(*x), x1.max(*x), y0.min(*y), y1.max(*y))
+    });
+    let mut svg = svg_start(WIDTH, HEIGHT, title);
+    if x_min > x_max {
+        let _ = writeln!(svg, r#"<text x="{}" y="{}" text-anchor="middle">No data</text>"#, WIDTH / 2.0, HEIGHT / 2.0);
+        svg += "</svg>\n";
+        return svg;
+    }
+    let (x_min, x_max, x_ticks) = get_ticks(x_min, x_max, 10);
+    let (y_min, y_max, y_ticks) = get_ticks(y_min, y_max, 6);
+    let plot_width = WIDTH - MARGIN_LEFT - MARGIN_RIGHT;
+    let plot_height = HEIGHT - MARGIN_TOP - MARGIN_BOTTOM;
+    let x_pos = |x: f32| MARGIN_LEFT + (x - x_min) / (x_max - x_min) * plot_width;
+    let y_pos = |y: f32| MARGIN_TOP + plot_height - (y - y_min) / (y_max - y_min) * plot_height;
+
+    for y in &y_ticks {
+        let _ = writeln!(svg, r##"<line x1="{}" y1="{:.1}" x2="{}" y2="{:.1}" stroke="#ddd"/>"##, MARGIN_LEFT, y_pos(*y), MARGIN_LEFT + plot_width, y_pos(*y));
+        let _ = writeln!(svg, r#"<text x="{}" y="{:.1}" text-anchor="end">{}</text>"#, MARGIN_LEFT - 6.0, y_pos(*y) + 4.0, format_value(*y));
+    }
+    for x in &x_ticks {
+        let _ = writeln!(svg, r#"<text x="{:.1}" y="{}" text-anchor="middle">{}</text>"#, x_pos(*x), MARGIN_TOP + plot_height + 18.0, format_value(*x));
+    }
+    let _ = writeln!(svg, r#"<rect x="{}" y="{}" width="{}" height="{}" fill="none" stroke="black"/>"#, MARGIN_LEFT, MARGIN_TOP, plot_width, plot_height);
+    let _ = writeln!(svg, r#"<text x="16" y="{}" text-anchor="middle" transform="rotate(-90 16 {})">{}</text>"#, MARGIN_TOP + plot_height / 2.0, MARGIN_TOP + plot_height / 2.0, escape(y_label));
+
+    let mut entries = Vec::new();
+    for (i, (name, points)) in series.iter().enumerate() {
+        let coordinates: Vec<String> = points.iter().map(|(x, y)| format!("{:.1},{:.1}", x_pos(*x), y_pos(*y))).collect();
+        let _ = writeln!(svg, r#"<polyline points="{}" fill="none" stroke="{}" stroke-width="2"/>"#, coordinates.join(" "), get_color(i));
+        entries.push((name.clone(), get_color(i)));
+    }
+    legend(&mut svg, WIDTH - MARGIN_RIGHT + 16.0, MARGIN_TOP, &entries);
+    svg += "</svg>\n";
+    return svg;
+}
+
+// Slices without a value are left out
+pub fn pie_chart(title: &str, slices: &[(String, f32, &str)]) -> String {
+    let (width, height, radius) = (420.0, 300.0, 110.0);
+    let (cx, cy) = (150.0, 170.0);
+    let mut svg = svg_start(width, height, title);
+    let total: f32 = slices.iter().map(|(_label, value, _color)| value.max(0.0)).sum();
+    if total <= 0.0 {
+        let _ = writeln!(svg, r#"<text x="{}" y="{}" text-anchor="middle">Nothing spent</text>"#, width / 2.0, height / 2.0);
+        svg += "</svg>\n";
+        return svg;
+    }
+
+    let mut angle = -std::f32::consts::FRAC_PI_2;
+    let mut entries = Vec::new();
+    for (label, value, color) in slices.iter().filter(|(_label, value, _color)| *value > 0.0) {
+        let share = value / total;
+        entries.push((format!("{} {} ({:.0}%)", label, format_value(*value), share * 100.0), *color));
+        if share >= 1.0 {
+            let _ = writeln!(svg, r#"<circle cx="{}" cy="{}" r="{}" fill="{}"/>"#, cx, cy, radius, color);
+            continue;
+        }
+        let end = angle + share * std::f32::consts::TAU;
+        let large_arc = if share > 0.5 { 1 } else { 0 };
+        let _ = writeln!(svg, r#"<path d="M {} {} L {:.2} {:.2} A {} {} 0 {} 1 {:.2} {:.2} Z" fill="{}" stroke="white"/>"#,
+            cx, cy, cx + radius * angle.cos(), cy + radius * angle.sin(), radius, radius, large_arc, cx + radius * end.cos(), cy + radius * end.sin(), color);
+        angle = end;
+    }
+    legend(&mut svg, cx + radius + 30.0, cy - 30.0, &entries);
+    svg += "</svg>\n";
+    return svg;
+}
+
+// Horizontal bars from the largest to the smallest value
+pub fn bar_chart(title: &str, unit: &str, bars: &[(String, f32)]) -> String {
+    let mut bars = bars.to_vec();
+    bars.sort_by(|(_a, a), (_b, b)| b.total_cmp(a));
+    let label_width = 160.0;
+    let plot_width = WIDTH - label_width - 90.0;
+    let height = MARGIN_TOP + bars.len() as f32 * (BAR_HEIGHT + BAR_GAP) + 30.0;
+    let mut svg = svg_start(WIDTH, height, &format!("{} ({})", title, unit));
+
+    let min = bars.iter().map(|(_label, value)| *value).fold(0.0f32, f32::min);
+    let max = bars.iter().map(|(_label, value)| *value).fold(0.0f32, f32::max);
+    let span = if max - min > 0.0 { max - min } else { 1.0 };
+    let x_pos = |value: f32| label_width + (value - min) / span * plot_width;
+    for (i, (label, value)) in bars.iter().enumerate() {
+        let top = MARGIN_TOP + i as f32 * (BAR_HEIGHT + BAR_GAP);
+        let (start, end) = (x_pos(0.0).min(x_pos(*value)), x_pos(0.0).max(x_pos(*value)));
+        let _ = writeln!(svg, r#"<text x="{}" y="{}" text-anchor="end">{}</text>"#, label_width - 8.0, top + BAR_HEIGHT / 2.0 + 4.0, escape(label));
+        let _ = writeln!(svg, r#"<rect x="{:.1}" y="{}" width="{:.1}" height="{}" fill="{}"/>"#, start, top, end - start, BAR_HEIGHT, get_color(i));
+        let _ = writeln!(svg, r#"<text x="{:.1}" y="{}">{}</text>"#, end + 6.0, top + BAR_HEIGHT / 2.0 + 4.0, format_value(*value));
+    }
+    svg += "</svg>\n";
+    return svg;
+}
+
+// The players, or without any the largest countries
+fn get_chart_countries(stats: &Eu4Stats) -> Vec<&CountryStats> {
+    let players: Vec<&CountryStats> = stats.countries.iter().filter(|c| c.player.is_some()).collect();
+    if !players.is_empty() {
+        return players;
+    }
+    let mut countries: Vec<&CountryStats> = stats.countries.iter().collect();
+    countries.sort_by(|a, b| b.country.total_development.total_cmp(&a.country.total_development));
+    countries.truncate(NUM_COUNTRIES);
+    return countries;
+}
+
+fn write_chart(paths: &mut Vec<PathBuf>, output_dir: &Path, file_name: &str, svg: &str) -> Result<(), Box<dyn Error>> {
+    let path = output_dir.join(file_name);
+    fs::write(&path, svg)?;
+    paths.push(path);
+    return Ok(());
+}
+
+// Writes income_history.svg, spent_developing_<tag>.svg per player and bars_<metric>.svg per key
+// stat into `output_dir`, and returns their paths
+pub fn write_charts<P>(stats: &Eu4Stats, output_dir: P) -> Result<Vec<PathBuf>, Box<dyn Error>>
+where P: AsRef<Path> {
+    let output_dir = output_dir.as_ref();
+    fs::create_dir_all(output_dir)?;
+    let countries = get_chart_countries(stats);
+    let mut paths = Vec::new();
+
+    // The history holds yearly income, the web app charts it monthly too
+    let series: Vec<(String, Vec<(f32, f32)>)> = countries.iter()
+        .map(|c| (get_label(c), c.country.income_history.iter().map(|(year, income)| (*year as f32, *income as f32 / 12.0)).collect()))
+        .collect();
+    write_chart(&mut paths, output_dir, "income_history.svg", &line_chart("Monthly income", "Ducats", &series))?;
+
+    for c in countries.iter().filter(|c| c.player.is_some()) {
+        let slices: Vec<(String, f32, &str)> = (0..3)
+            .map(|i| (MANA_LABELS[i].to_string(), c.mana.spent_developing[i] as f32, MANA_COLORS[i]))
+            .collect();
+        let svg = pie_chart(&format!("Spent developing: {}", get_label(c)), &slices);
+        write_chart(&mut paths, output_dir, &format!("spent_developing_{}.svg", c.tag), &svg)?;
+    }
+
+    for metric in BAR_METRICS {
+        let Some(definition) = stats.metrics.iter().find(|d| d.key == metric) else { continue };
+        let bars: Vec<(String, f32)> = countries.iter()
+            .filter_map(|c| c.metric_values.get(metric).map(|value| (get_label(c), *value)))
+            .collect();
+        if bars.is_empty() {
+            continue;
+        }
+        let svg = bar_chart(&definition.label, &definition.unit, &bars);
+        write_chart(&mut paths, output_dir, &format!("bars_{}.svg", metric.replace('.', "_")), &svg)?;
+    }
+    info!("Finished writing {} charts to {:?}", paths.len(), output_dir);
+    return Ok(paths);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ticks() {
+        assert_eq!(nice_step(100.0, 5), 20.0);
+        assert_eq!(nice_step(7.0, 6), 2.0);
+        assert_eq!(get_ticks(1444.0, 1663.0, 10), (1400.0, 1700.0, vec![1400.0, 1450.0, 1500.0, 1550.0, 1600.0, 1650.0, 1700.0]));
+        assert_eq!(get_ticks(0.0, 0.0, 6), (0.0, 1.0, vec![0.0, 1.0]));
+    }
+
+    #[test]
+    fn pie_slices() {
+        let svg = pie_chart("Spent developing", &[("Admin".to_string(), 300.0, "red"), ("Diplo".to_string(), 100.0, "blue"), ("Military".to_string(), 0.0, "green")]);
+        assert_eq!(svg.matches("<path").count(), 2);
+        assert!(svg.contains("Admin 300 (75%)"));
+        assert!(!svg.contains("Military"));
+
+        let svg = pie_chart("Spent developing", &[("Admin".to_string(), 300.0, "red")]);
+        assert_eq!(svg.matches("<circle").count(), 1);
+    }
+
+    #[test]
+    fn escapes_names() {
+        let svg = bar_chart("Income", "ducats", &[("A & B <x>".to_string(), 10.0)]);
+        assert!(svg.contains("A &amp; B &lt;x&gt;"));
+        assert!(svg.starts_with("<svg") && svg.ends_with("</svg>\n"));
+    }
+}
@@ -0,0 +1,148 @@
+use log::info;
+use std::collections::HashSet;
+use std::error::Error;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+use std::result::Result;
+
+use eu4save::{CountryTag, Eu4Date};
+use eu4save::query::Query;
+
+use crate::extras::{Extras, Relationship};
+use crate::models::{DiplomacyGraph, DiplomacyStats, DiplomaticRelation, Eu4Stats, GraphEdge, GraphNode, Opinion};
+
+const MUTUAL_RELATIONS: [&str; 2] = ["alliance", "royal_marriage"];
+
+fn format_date(date: Option<Eu4Date>) -> Option<String> {
+    return date.map(|d| d.iso_8601().to_string());
+}
+
+fn relation(kind: &str, first: &CountryTag, second: &CountryTag, start_date: Option<Eu4Date>) -> DiplomaticRelation {
+    return DiplomaticRelation {
+        kind: kind.to_string(),
+        first: first.to_string(),
+        second: second.to_string(),
+        start_date: format_date(start_date),
+    };
+}
+
+fn relationships(kind: &str, relationships: &[Relationship]) -> Vec<DiplomaticRelation> {
+    return relationships.iter().map(|r| relation(kind, &r.first, &r.second, r.start_date)).collect();
+}
+
+// Keeps relations that involve at least one of the `included` countries, and opinions between two of them
+pub fn generate_diplomacy(save_query: &Query, extras: &Extras, included: &HashSet<CountryTag>) -> Result<DiplomacyStats, Box<dyn Error>> {
+    let diplomacy = &extras.diplomacy;
+    let mut relations = Vec::new();
+    relations.extend(relationships("alliance", &diplomacy.alliance));
+    relations.extend(relationships("royal_marriage", &diplomacy.royal_marriage));
+    relations.extend(relationships("guarantee", &diplomacy.guarantee));
+    relations.extend(relationships("military_access", &diplomacy.military_access));
+    relations.extend(relationships("fleet_access", &diplomacy.fleet_access));
+    for dependency in &save_query.save().game.diplomacy.dependencies {
+        relations.push(relation("subject", &dependency.first, &dependency.second, dependency.start_date));
+    }
+
+    let mut opinions = Vec::new();
+    for (tag, country) in &extras.countries {
+        for rival in &country.rival {
+            relations.push(relation("rival", tag, &rival.country, rival.date));
+        }
+        for embargoed in &country.trade_embargoes {
+            relations.push(relation("embargo", tag, embargoed, None));
+        }
+        if !included.contains(tag) {
+            continue;
+        }
+        for (other, active_relation) in &country.active_relations {
+            if included.contains(other) && active_relation.cached_sum != 0.0 {
+                opinions.push(Opinion {
+                    from: tag.to_string(),
+                    to: other.to_string(),
+                    opinion: active_relation.cached_sum,
+                });
+            }
+        }
+    }
+
+    let included: HashSet<String> = included.iter().map(|t| t.to_string()).collect();
+    relations.retain(|r| included.contains(&r.first) || included.contains(&r.second));
+    relations.sort_by(|a, b| (&a.kind, &a.first, &a.second).cmp(&(&b.kind, &b.first, &b.second)));
+    opinions.sort_by(|a, b| (&a.from, &a.to).cmp(&(&b.from, &b.to)));
+
+    let stats = DiplomacyStats {
+        relations,
+        opinions,
+    };
+    Ok(stats)
+}
+
+// Only relations between two countries in the output become edges
+pub fn build_graph(stats: &Eu4Stats) -> DiplomacyGraph {
+    let tags: HashSet<&str> = stats.countries.iter().map(|c| c.tag.as_str()).collect();
+    return DiplomacyGraph {
+        nodes: stats.countries.iter().map(|c| GraphNode {
+            id: c.tag.clone(),
+            name: c.name.clone(),
+            player: c.player.clone(),
+        }).collect(),
+        edges: stats.diplomacy.relations.iter()
+            .filter(|r| tags.contains(r.first.as_str()) && tags.contains(r.second.as_str()))
+            .map(|r| GraphEdge {
+                source: r.first.clone(),
+                target: r.second.clone(),
+                kind: r.kind.clone(),
+                directed: !MUTUAL_RELATIONS.contains(&r.kind.as_str()),
+            }).collect(),
+    };
+}
+
+fn edge_colour(kind: &str) -> &'static str {
+    match kind {
+        "alliance" => "blue",
+        "royal_marriage" => "purple",
+        "guarantee" => "darkgreen",
+        "military_access" | "fleet_access" => "gray",
+        "subject" => "black",
+        "rival" => "red",
+        "embargo" => "orange",
+        _ => "black",
+    }
+}
+
+pub fn to_dot(graph: &DiplomacyGraph) -> String {
+    let mut dot = String::from("digraph diplomacy {\n");
+    for node in &graph.nodes {
+        let label = match &node.player {
+            Some(player) => format!("{}\\n({})", node.name, player),
+            None => node.name.clone(),
+        };
+        let style = if node.player.is_some() { ", style=bold" } else { "" };
+        dot.push_str(&format!("    \"{}\" [label=\"{}\"{}];\n", node.id, label.replace('"', "\\\""), style));
+    }
+    for edge in &graph.edges {
+        let dir = if edge.directed { "" } else { ", dir=none" };
+        dot.push_str(&format!(
+            "    \"{}\" -> \"{}\" [label=\"{}\", color={}{}];\n",
+            edge.source, edge.target, edge.kind, edge_colour(&edge.kind), dir
+        ));
+    }
+    dot.push_str("}\n");
+    return dot;
+}
+
+// Writes GraphViz DOT for `.dot` and `.gv` paths, and JSON nodes and edges otherwise
+pub fn write_graph<P>(stats: &Eu4Stats, graph_path: P) -> Result<(), Box<dyn Error>>
+where P: AsRef<Path> {
+    let graph = build_graph(stats);
+    let file = File::create(&graph_path)?;
+    let mut writer = BufWriter::new(file);
+    match graph_path.as_ref().extension().and_then(|e| e.to_str()) {
+        Some("dot") | Some("gv") => writer.write_all(to_dot(&graph).as_bytes())?,
+        _ => serde_json::to_writer(&mut writer, &graph)?,
+    }
+    writer.flush()?;
+    info!("Finished writing diplomacy graph to {:?}", graph_path.as_ref());
+    return Ok(());
+}
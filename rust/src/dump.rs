@@ -0,0 +1,234 @@
+use log::info;
+use std::collections::{HashMap, HashSet};
+use std::error::Error;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::result::Result;
+
+use eu4save::{CountryTag, Eu4Date};
+use eu4save::query::Query;
+use regex::Regex;
+
+use crate::extras::Extras;
+use crate::models::{CountryDump, ModifierDump};
+
+// A glob such as `anb_*`, or a regular expression when prefixed with `re:`
+fn compile_pattern(pattern: &str) -> Result<Regex, Box<dyn Error>> {
+    if let Some(regex) = pattern.strip_prefix("re:") {
+        return Ok(Regex::new(regex)?);
+    }
+    let glob = regex::escape(pattern).replace("\\*", ".*").replace("\\?", ".");
+    return Ok(Regex::new(&format!("^{}$", glob))?);
+}
+
+// Which entries end up in the dump. Each flag may be given several times and keeps the entries
+// matching any of its patterns. As soon as one filter is given, the kinds without a filter are
+// left out, so `--flag 'anb_*'` dumps only those flags.
+#[derive(Debug, Default)]
+pub struct DumpFilter {
+    pub flags: Vec<Regex>,
+    pub variables: Vec<Regex>,
+    pub modifiers: Vec<Regex>,
+    pub policies: Vec<Regex>,
+}
+
+fn keep(patterns: &[Regex], filtered: bool, name: &str) -> bool {
+    if !filtered {
+        return true;
+    }
+    return patterns.iter().any(|p| p.is_match(name));
+}
+
+impl DumpFilter {
+    // Removes the filter flags from `args`, like `Selection::from_args`
+    pub fn from_args(args: &mut Vec<String>) -> Result<DumpFilter, Box<dyn Error>> {
+        let mut filter = DumpFilter::default();
+        let mut i = 0;
+        while i < args.len() {
+            let patterns = match args[i].as_str() {
+                "--flag" => &mut filter.flags,
+                "--variable" => &mut filter.variables,
+                "--modifier" => &mut filter.modifiers,
+                "--policy" => &mut filter.policies,
+                _ => {
+                    i += 1;
+                    continue;
+                }
+            };
+            if i + 1 >= args.len() {
+                return Err(format!("Missing value for {}", args[i]).into());
+            }
+            patterns.push(compile_pattern(&args.remove(i + 1))?);
+            args.remove(i);
+        }
+        return Ok(filter);
+    }
+
+    fn is_filtered(&self) -> bool {
+        return !self.flags.is_empty() || !self.variables.is_empty() || !self.modifiers.is_empty() || !self.policies.is_empty();
+    }
+
+    fn keep_flag(&self, name: &str) -> bool {
+        return keep(&self.flags, self.is_filtered(), name);
+    }
+
+    fn keep_variable(&self, name: &str) -> bool {
+        return keep(&self.variables, self.is_filtered(), name);
+    }
+
+    fn keep_modifier(&self, name: &str) -> bool {
+        return keep(&self.modifiers, self.is_filtered(), name);
+    }
+
+    fn keep_policy(&self, name: &str) -> bool {
+        return keep(&self.policies, self.is_filtered(), name);
+    }
+}
+
+fn dump_flags(flags: &HashMap<String, Eu4Date>, filter: &DumpFilter) -> Vec<(String, String)> {
+    return flags.iter()
+        .filter(|(name, _date)| filter.keep_flag(name))
+        .map(|(name, date)| (name.clone(), date.iso_8601().to_string()))
+        .collect();
+}
+
+fn is_empty(dump: &CountryDump) -> bool {
+    return dump.flags.is_empty() && dump.hidden_flags.is_empty() && dump.variables.is_empty()
+        && dump.triggered_modifiers.is_empty() && dump.event_modifiers.is_empty() && dump.active_policies.is_empty();
+}
+
+// When filtering, countries without a single matching entry are left out
+pub fn generate_dump(
+    save_query: &Query,
+    extras: &Extras,
+    localisation_map: &HashMap<String, String>,
+    players: &HashMap<CountryTag, String>,
+    included: &HashSet<CountryTag>,
+    filter: &DumpFilter) -> Result<Vec<CountryDump>, Box<dyn Error>> {
+
+    let mut dumps = Vec::new();
+    for c in save_query.countries() {
+        if !included.contains(&c.tag) {
+            continue;
+        }
+        let tag = c.tag.to_string();
+        let mut dump = CountryDump {
+            name: localisation_map.get(&tag).unwrap_or(&tag).to_string(),
+            tag,
+            player: players.get(&c.tag).cloned(),
+            active_policies: c.country.active_policies.iter()
+                .map(|p| p.policy.clone())
+                .filter(|p| filter.keep_policy(p))
+                .collect(),
+            ..Default::default()
+        };
+        if let Some(extra) = extras.countries.get(&c.tag) {
+            dump.flags = dump_flags(&extra.flags, filter).into_iter().collect();
+            dump.hidden_flags = dump_flags(&extra.hidden_flags, filter).into_iter().collect();
+            dump.variables = extra.variables.iter()
+                .filter(|(name, _value)| filter.keep_variable(name))
+                .map(|(name, value)| (name.clone(), *value))
+                .collect();
+            dump.triggered_modifiers = extra.triggered_modifier.iter()
+                .map(|m| m.0.clone())
+                .filter(|m| filter.keep_modifier(m))
+                .collect();
+            dump.event_modifiers = extra.modifier.iter()
+                .filter(|m| filter.keep_modifier(&m.modifier))
+                .map(|m| ModifierDump {
+                    name: m.modifier.clone(),
+                    expires: if m.permanent { None } else { m.date.map(|d| d.iso_8601().to_string()) },
+                    permanent: m.permanent,
+                })
+                .collect();
+        }
+        if filter.is_filtered() && is_empty(&dump) {
+            continue;
+        }
+        dumps.push(dump);
+    }
+    return Ok(dumps);
+}
+
+// Writes pretty-printed JSON to `dump_path`, or to stdout without one
+pub fn write_dump(dumps: &[CountryDump], dump_path: Option<&str>) -> Result<(), Box<dyn Error>> {
+    let mut writer: Box<dyn Write> = match dump_path {
+        Some(path) => Box::new(BufWriter::new(File::create(path)?)),
+        None => Box::new(BufWriter::new(io::stdout())),
+    };
+    serde_json::to_writer_pretty(&mut writer, dumps)?;
+    writeln!(writer)?;
+    writer.flush()?;
+    info!("Dumped {} countries", dumps.len());
+    return Ok(());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(args: &[&str]) -> Vec<String> {
+        return args.iter().map(|a| a.to_string()).collect();
+    }
+
+    #[test]
+    fn patterns() {
+        let cases = [
+            ("anb_*", "anb_magic", true),
+            ("anb_*", "anb_", true),
+            ("anb_*", "xanb_magic", false),
+            ("anb_?", "anb_1", true),
+            ("anb_?", "anb_12", false),
+            // Everything but the wildcards is literal
+            ("a.b", "a.b", true),
+            ("a.b", "axb", false),
+            ("a+(b)", "a+(b)", true),
+            ("re:^anb_(magic|mage)", "anb_mage_tower", true),
+            ("re:^anb_(magic|mage)", "anb_other", false),
+            // Regular expressions aren't anchored
+            ("re:mage", "anb_mage_tower", true),
+        ];
+        for (pattern, name, expected) in cases {
+            assert_eq!(compile_pattern(pattern).unwrap().is_match(name), expected, "{} {}", pattern, name);
+        }
+        assert!(compile_pattern("re:(").is_err());
+    }
+
+    #[test]
+    fn from_args() {
+        let mut arguments = args(&["rust", "dump", "--flag", "anb_*", "loc.yml", "--flag", "re:^x", "save.eu4", "--policy", "p_*"]);
+        let filter = DumpFilter::from_args(&mut arguments).unwrap();
+        assert_eq!(arguments, args(&["rust", "dump", "loc.yml", "save.eu4"]));
+        assert_eq!(filter.flags.len(), 2);
+        assert_eq!(filter.policies.len(), 1);
+        assert!(filter.variables.is_empty() && filter.modifiers.is_empty());
+
+        assert!(DumpFilter::from_args(&mut args(&["rust", "dump", "--variable"])).is_err());
+    }
+
+    #[test]
+    fn unfiltered_keeps_everything() {
+        let filter = DumpFilter::default();
+        assert!(filter.keep_flag("anything"));
+        assert!(filter.keep_variable("anything"));
+        assert!(filter.keep_modifier("anything"));
+        assert!(filter.keep_policy("anything"));
+    }
+
+    // One filter hides the kinds without a filter
+    #[test]
+    fn filter_hides_other_kinds() {
+        let filter = DumpFilter::from_args(&mut args(&["--flag", "anb_*"])).unwrap();
+        assert!(filter.keep_flag("anb_magic"));
+        assert!(!filter.keep_flag("other_flag"));
+        assert!(!filter.keep_variable("anb_magic"));
+        assert!(!filter.keep_modifier("anb_magic"));
+        assert!(!filter.keep_policy("anb_magic"));
+
+        let filter = DumpFilter::from_args(&mut args(&["--modifier", "a_*", "--modifier", "b_*"])).unwrap();
+        assert!(filter.keep_modifier("a_1"));
+        assert!(filter.keep_modifier("b_1"));
+        assert!(!filter.keep_modifier("c_1"));
+        assert!(!filter.keep_flag("a_1"));
+    }
+}
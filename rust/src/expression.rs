@@ -0,0 +1,243 @@
+// A small arithmetic language for user-defined metrics, e.g. `income / number_provinces` or
+// `powers_earned[2] - mana.spent_generals`. It has numbers, field paths, `+ - * /`, unary minus
+// and parentheses. What a field path means is up to whoever evaluates the expression.
+use std::error::Error;
+use std::fmt;
+use std::result::Result;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Expression {
+    Number(f32),
+    Field(String),
+    Negate(Box<Expression>),
+    Binary(Operator, Box<Expression>, Box<Expression>),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Operator {
+    Add,
+    Subtract,
+    Multiply,
+    Divide,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Number(f32),
+    Field(String),
+    Operator(Operator),
+    Open,
+    Close,
+}
+
+#[derive(Debug)]
+pub struct ParseError {
+    pub expression: String,
+    pub message: String,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Invalid expression {:?}: {}", self.expression, self.message)
+    }
+}
+
+impl Error for ParseError {}
+
+fn is_field_char(c: char) -> bool {
+    return c.is_ascii_alphanumeric() || c == '_' || c == '.' || c == '[' || c == ']';
+}
+
+fn tokenize(source: &str) -> Result<Vec<Token>, String> {
+    let mut tokens = Vec::new();
+    let chars: Vec<char> = source.chars().collect();
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        let token = match c {
+            ' ' | '\t' | '\n' | '\r' => {
+                i += 1;
+                continue;
+            }
+            '+' => Token::Operator(Operator::Add),
+            '-' => Token::Operator(Operator::Subtract),
+            '*' => Token::Operator(Operator::Multiply),
+            '/' => Token::Operator(Operator::Divide),
+            '(' => Token::Open,
+            ')' => Token::Close,
+            _ if c.is_ascii_digit() || c == '.' => {
+                let start = i;
+                while i < chars.len() && (chars[i].is_ascii_digit() || chars[i] == '.') {
+                    i += 1;
+                }
+                let number: String = chars[start..i].iter().collect();
+                tokens.push(Token::Number(number.parse().map_err(|_| format!("bad number {:?}", number))?));
+                continue;
+            }
+            _ if c.is_ascii_alphabetic() || c == '_' => {
+                let start = i;
+                while i < chars.len() && is_field_char(chars[i]) {
+                    i += 1;
+                }
+                tokens.push(Token::Field(chars[start..i].iter().collect()));
+                continue;
+            }
+            _ => return Err(format!("unexpected {:?}", c)),
+        };
+        tokens.push(token);
+        i += 1;
+    }
+    return Ok(tokens);
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    position: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        return self.tokens.get(self.position);
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.position).cloned();
+        self.position += 1;
+        return token;
+    }
+
+    // sum = product (('+' | '-') product)*
+    fn sum(&mut self) -> Result<Expression, String> {
+        let mut left = self.product()?;
+        while let Some(Token::Operator(operator @ (Operator::Add | Operator::Subtract))) = self.peek() {
+            let operator = *operator;
+            self.position += 1;
+            left = Expression::Binary(operator, Box::new(left), Box::new(self.product()?));
+        }
+        return Ok(left);
+    }
+
+    // product = unary (('*' | '/') unary)*
+    fn product(&mut self) -> Result<Expression, String> {
+        let mut left = self.unary()?;
+        while let Some(Token::Operator(operator @ (Operator::Multiply | Operator::Divide))) = self.peek() {
+            let operator = *operator;
+            self.position += 1;
+            left = Expression::Binary(operator, Box::new(left), Box::new(self.unary()?));
+        }
+        return Ok(left);
+    }
+
+    // unary = '-' unary | number | field | '(' sum ')'
+    fn unary(&mut self) -> Result<Expression, String> {
+        return match self.next() {
+            Some(Token::Operator(Operator::Subtract)) => Ok(Expression::Negate(Box::new(self.unary()?))),
+            Some(Token::Number(n)) => Ok(Expression::Number(n)),
+            Some(Token::Field(f)) => Ok(Expression::Field(f)),
+            Some(Token::Open) => {
+                let inner = self.sum()?;
+                match self.next() {
+                    Some(Token::Close) => Ok(inner),
+                    _ => Err("missing )".to_string()),
+                }
+            }
+            Some(token) => Err(format!("unexpected {:?}", token)),
+            None => Err("unexpected end".to_string()),
+        };
+    }
+}
+
+impl Expression {
+    pub fn parse(source: &str) -> Result<Expression, ParseError> {
+        let error = |message: String| ParseError { expression: source.to_string(), message };
+        let mut parser = Parser { tokens: tokenize(source).map_err(error)?, position: 0 };
+        let expression = parser.sum().map_err(error)?;
+        if let Some(token) = parser.peek() {
+            return Err(error(format!("unexpected {:?}", token)));
+        }
+        return Ok(expression);
+    }
+
+    // The field paths, in the order they appear
+    pub fn fields(&self) -> Vec<&str> {
+        return match self {
+            Expression::Number(_n) => Vec::new(),
+            Expression::Field(f) => vec![f.as_str()],
+            Expression::Negate(e) => e.fields(),
+            Expression::Binary(_operator, l, r) => {
+                let mut fields = l.fields();
+                fields.extend(r.fields());
+                fields
+            }
+        };
+    }
+
+    // `None` when `resolve` doesn't know a field. Division by zero gives infinity or NaN, like f32.
+    pub fn evaluate<F>(&self, resolve: &F) -> Option<f32>
+    where F: Fn(&str) -> Option<f32> {
+        return match self {
+            Expression::Number(n) => Some(*n),
+            Expression::Field(f) => resolve(f),
+            Expression::Negate(e) => e.evaluate(resolve).map(|v| -v),
+            Expression::Binary(operator, l, r) => {
+                let l = l.evaluate(resolve)?;
+                let r = r.evaluate(resolve)?;
+                Some(match operator {
+                    Operator::Add => l + r,
+                    Operator::Subtract => l - r,
+                    Operator::Multiply => l * r,
+                    Operator::Divide => l / r,
+                })
+            }
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn evaluate(source: &str) -> Option<f32> {
+        let resolve = |field: &str| match field {
+            "income" => Some(12.0),
+            "number_provinces" => Some(4.0),
+            "powers_earned[2]" => Some(900.0),
+            "mana.spent_generals" => Some(150.0),
+            _ => None,
+        };
+        return Expression::parse(source).unwrap().evaluate(&resolve);
+    }
+
+    #[test]
+    fn fields_and_operators() {
+        assert_eq!(evaluate("income / number_provinces"), Some(3.0));
+        assert_eq!(evaluate("powers_earned[2] - mana.spent_generals"), Some(750.0));
+        assert_eq!(evaluate("income * 0.5 + 1"), Some(7.0));
+    }
+
+    #[test]
+    fn precedence_and_parentheses() {
+        assert_eq!(evaluate("1 + 2 * 3"), Some(7.0));
+        assert_eq!(evaluate("(1 + 2) * 3"), Some(9.0));
+        assert_eq!(evaluate("10 - 4 - 3"), Some(3.0));
+        assert_eq!(evaluate("-income + -(2 - 4)"), Some(-10.0));
+    }
+
+    #[test]
+    fn field_list() {
+        let expression = Expression::parse("-(income - 2) / number_provinces * income + powers_earned[2]").unwrap();
+        assert_eq!(expression.fields(), ["income", "number_provinces", "income", "powers_earned[2]"]);
+    }
+
+    #[test]
+    fn unknown_field() {
+        assert_eq!(evaluate("income / treasury"), None);
+    }
+
+    #[test]
+    fn invalid_expressions() {
+        for source in ["", "income +", "(income", "income)", "income % 2", "1 2"] {
+            assert!(Expression::parse(source).is_err(), "{:?} should not parse", source);
+        }
+    }
+}
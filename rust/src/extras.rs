@@ -0,0 +1,381 @@
+// Parts of the gamestate that eu4save's models leave out. They are deserialized in a second pass
+// over the save, and every field defaults so a save without them still parses.
+use std::collections::HashMap;
+use std::fmt;
+
+use eu4save::{CountryTag, Eu4Date};
+use jomini::JominiDeserialize;
+use serde::de::{Deserialize, Deserializer, IgnoredAny, MapAccess, SeqAccess, Visitor};
+
+#[derive(Debug, Clone, JominiDeserialize)]
+pub struct Relationship {
+    pub first: CountryTag,
+    pub second: CountryTag,
+    #[jomini(default)]
+    pub start_date: Option<Eu4Date>,
+}
+
+#[derive(Debug, Clone, JominiDeserialize, Default)]
+pub struct Diplomacy {
+    #[jomini(default, duplicated)]
+    pub alliance: Vec<Relationship>,
+    #[jomini(default, duplicated)]
+    pub royal_marriage: Vec<Relationship>,
+    #[jomini(default, duplicated)]
+    pub guarantee: Vec<Relationship>,
+    #[jomini(default, duplicated)]
+    pub military_access: Vec<Relationship>,
+    #[jomini(default, duplicated)]
+    pub fleet_access: Vec<Relationship>,
+}
+
+#[derive(Debug, Clone, JominiDeserialize)]
+pub struct Rival {
+    pub country: CountryTag,
+    #[jomini(default)]
+    pub date: Option<Eu4Date>,
+}
+
+#[derive(Debug, Clone, JominiDeserialize, Default)]
+pub struct ActiveRelation {
+    #[jomini(default)]
+    pub cached_sum: f32,
+}
+
+#[derive(Debug, Clone, JominiDeserialize, Default)]
+pub struct Losses {
+    #[jomini(default)]
+    pub members: Vec<u32>,
+}
+
+#[derive(Debug, Clone, JominiDeserialize, Default)]
+pub struct EstateModifier {
+    #[jomini(default)]
+    pub value: f32,
+}
+
+#[derive(Debug, Clone, JominiDeserialize)]
+pub struct Estate {
+    #[jomini(alias = "type")]
+    pub kind: String,
+    #[jomini(default)]
+    pub loyalty: f32,
+    #[jomini(default)]
+    pub territory: f32,
+    #[jomini(default, duplicated)]
+    pub influence_modifier: Vec<EstateModifier>,
+}
+
+#[derive(Debug, Clone, JominiDeserialize, Default)]
+pub struct ReformStack {
+    #[jomini(default)]
+    pub reforms: Vec<String>,
+}
+
+#[derive(Debug, Clone, JominiDeserialize, Default)]
+pub struct Government {
+    #[jomini(default)]
+    pub government: String,
+    #[jomini(default)]
+    pub reform_stack: ReformStack,
+}
+
+#[derive(Debug, Clone, JominiDeserialize)]
+pub struct EventModifier {
+    pub modifier: String,
+    #[jomini(default)]
+    pub date: Option<Eu4Date>,
+    #[jomini(default)]
+    pub permanent: bool,
+}
+
+// Triggered modifiers are written either as a bare name or as a block holding `modifier`
+#[derive(Debug, Clone, Default)]
+pub struct ModifierName(pub String);
+
+impl<'de> Deserialize<'de> for ModifierName {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where D: Deserializer<'de>, {
+        struct ModifierNameVisitor;
+
+        impl<'de> Visitor<'de> for ModifierNameVisitor {
+            type Value = ModifierName;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                formatter.write_str("a modifier name")
+            }
+
+            fn visit_str<E>(self, v: &str) -> Result<ModifierName, E>
+            where E: serde::de::Error, {
+                Ok(ModifierName(v.to_string()))
+            }
+
+            fn visit_map<A>(self, mut map: A) -> Result<ModifierName, A::Error>
+            where A: MapAccess<'de>, {
+                let mut name = ModifierName::default();
+                while let Some(key) = map.next_key::<String>()? {
+                    if key == "modifier" {
+                        name.0 = map.next_value()?;
+                    } else {
+                        map.next_value::<IgnoredAny>()?;
+                    }
+                }
+                Ok(name)
+            }
+        }
+
+        deserializer.deserialize_any(ModifierNameVisitor)
+    }
+}
+
+// The colour a country has on the political map
+#[derive(Debug, Clone, JominiDeserialize, Default)]
+pub struct CountryColors {
+    #[jomini(default)]
+    pub map_color: Vec<u8>,
+}
+
+#[derive(Debug, Clone, JominiDeserialize, Default)]
+pub struct ExtraCountry {
+    #[jomini(default, duplicated)]
+    pub rival: Vec<Rival>,
+    #[jomini(default)]
+    pub trade_embargoes: Vec<CountryTag>,
+    #[jomini(default)]
+    pub active_relations: HashMap<CountryTag, ActiveRelation>,
+    #[jomini(default)]
+    pub losses: Losses,
+    #[jomini(default, duplicated)]
+    pub accepted_culture: Vec<String>,
+    #[jomini(default)]
+    pub religious_unity: Option<f32>,
+    #[jomini(default)]
+    pub tolerance_own: Option<f32>,
+    #[jomini(default)]
+    pub tolerance_heretic: Option<f32>,
+    #[jomini(default)]
+    pub tolerance_heathen: Option<f32>,
+    #[jomini(default, duplicated)]
+    pub estate: Vec<Estate>,
+    #[jomini(default)]
+    pub government: Option<Government>,
+    #[jomini(default)]
+    pub flags: HashMap<String, Eu4Date>,
+    #[jomini(default)]
+    pub hidden_flags: HashMap<String, Eu4Date>,
+    #[jomini(default)]
+    pub variables: HashMap<String, f32>,
+    #[jomini(default, duplicated)]
+    pub modifier: Vec<EventModifier>,
+    #[jomini(default, duplicated)]
+    pub triggered_modifier: Vec<ModifierName>,
+    #[jomini(default)]
+    pub colors: Option<CountryColors>,
+}
+
+#[derive(Debug, Clone, JominiDeserialize)]
+pub struct WarGoal {
+    #[jomini(default, alias = "type")]
+    pub kind: Option<String>,
+    #[jomini(default)]
+    pub casus_belli: Option<String>,
+}
+
+#[derive(Debug, Clone, JominiDeserialize)]
+pub struct ExtraWar {
+    pub name: String,
+    #[jomini(default)]
+    pub war_goal: Option<WarGoal>,
+    #[jomini(default)]
+    pub attacker_score: Option<f32>,
+}
+
+#[derive(Debug, Clone, JominiDeserialize, Default)]
+pub struct TradeNodeCountry {
+    #[jomini(default)]
+    pub val: f32,
+    #[jomini(default)]
+    pub money: f32,
+    #[jomini(default)]
+    pub has_trader: bool,
+    #[jomini(default)]
+    pub has_capital: bool,
+    #[jomini(default, alias = "type")]
+    pub kind: i32,
+}
+
+// Each country with trade power in a node is stored under its tag next to the node's own fields
+#[derive(Debug, Clone, Default)]
+pub struct TradeNode {
+    pub definitions: String,
+    pub current: f32,
+    pub local_value: f32,
+    pub total: f32,
+    pub countries: Vec<(CountryTag, TradeNodeCountry)>,
+}
+
+fn is_country_tag(key: &str) -> bool {
+    return key.len() == 3 && key.chars().all(|c| c.is_ascii_uppercase() || c.is_ascii_digit() || c == '-');
+}
+
+impl<'de> Deserialize<'de> for TradeNode {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where D: Deserializer<'de>, {
+        struct TradeNodeVisitor;
+
+        impl<'de> Visitor<'de> for TradeNodeVisitor {
+            type Value = TradeNode;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                formatter.write_str("a trade node")
+            }
+
+            fn visit_map<A>(self, mut map: A) -> Result<TradeNode, A::Error>
+            where A: MapAccess<'de>, {
+                let mut node = TradeNode::default();
+                while let Some(key) = map.next_key::<String>()? {
+                    match key.as_str() {
+                        "definitions" => node.definitions = map.next_value()?,
+                        "current" => node.current = map.next_value()?,
+                        "local_value" => node.local_value = map.next_value()?,
+                        "total" => node.total = map.next_value()?,
+                        _ => match key.parse::<CountryTag>() {
+                            Ok(tag) if is_country_tag(&key) => node.countries.push((tag, map.next_value()?)),
+                            _ => {
+                                map.next_value::<IgnoredAny>()?;
+                            }
+                        },
+                    }
+                }
+                Ok(node)
+            }
+        }
+
+        deserializer.deserialize_map(TradeNodeVisitor)
+    }
+}
+
+#[derive(Debug, Clone, JominiDeserialize, Default)]
+pub struct Trade {
+    #[jomini(default, duplicated)]
+    pub node: Vec<TradeNode>,
+}
+
+// The tags listed anywhere under a block, whether as `country="TAG"` entries or in nested blocks
+#[derive(Debug, Clone, Default)]
+struct TagList(Vec<CountryTag>);
+
+impl<'de> Deserialize<'de> for TagList {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where D: Deserializer<'de>, {
+        struct TagListVisitor;
+
+        impl<'de> Visitor<'de> for TagListVisitor {
+            type Value = TagList;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                formatter.write_str("a list of countries")
+            }
+
+            fn visit_str<E>(self, _v: &str) -> Result<TagList, E>
+            where E: serde::de::Error, {
+                Ok(TagList::default())
+            }
+
+            fn visit_f64<E>(self, _v: f64) -> Result<TagList, E>
+            where E: serde::de::Error, {
+                Ok(TagList::default())
+            }
+
+            fn visit_i64<E>(self, _v: i64) -> Result<TagList, E>
+            where E: serde::de::Error, {
+                Ok(TagList::default())
+            }
+
+            fn visit_u64<E>(self, _v: u64) -> Result<TagList, E>
+            where E: serde::de::Error, {
+                Ok(TagList::default())
+            }
+
+            fn visit_bool<E>(self, _v: bool) -> Result<TagList, E>
+            where E: serde::de::Error, {
+                Ok(TagList::default())
+            }
+
+            fn visit_seq<A>(self, mut seq: A) -> Result<TagList, A::Error>
+            where A: SeqAccess<'de>, {
+                let mut tags = TagList::default();
+                while let Some(list) = seq.next_element::<TagList>()? {
+                    tags.0.extend(list.0);
+                }
+                Ok(tags)
+            }
+
+            fn visit_map<A>(self, mut map: A) -> Result<TagList, A::Error>
+            where A: MapAccess<'de>, {
+                let mut tags = TagList::default();
+                while let Some(key) = map.next_key::<String>()? {
+                    if key == "country" {
+                        tags.0.push(map.next_value()?);
+                    } else {
+                        tags.0.extend(map.next_value::<TagList>()?.0);
+                    }
+                }
+                Ok(tags)
+            }
+        }
+
+        deserializer.deserialize_any(TagListVisitor)
+    }
+}
+
+// The current great powers, in the `original` entries of the `great_powers` block. Countries
+// that are `leaving` have already lost the status.
+#[derive(Debug, Clone, Default)]
+pub struct GreatPowers(pub Vec<CountryTag>);
+
+impl<'de> Deserialize<'de> for GreatPowers {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where D: Deserializer<'de>, {
+        struct GreatPowersVisitor;
+
+        impl<'de> Visitor<'de> for GreatPowersVisitor {
+            type Value = GreatPowers;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                formatter.write_str("the great powers")
+            }
+
+            fn visit_map<A>(self, mut map: A) -> Result<GreatPowers, A::Error>
+            where A: MapAccess<'de>, {
+                let mut great_powers = GreatPowers::default();
+                while let Some(key) = map.next_key::<String>()? {
+                    if key == "original" {
+                        great_powers.0.extend(map.next_value::<TagList>()?.0);
+                    } else {
+                        map.next_value::<IgnoredAny>()?;
+                    }
+                }
+                Ok(great_powers)
+            }
+        }
+
+        deserializer.deserialize_map(GreatPowersVisitor)
+    }
+}
+
+#[derive(Debug, Clone, JominiDeserialize, Default)]
+pub struct Extras {
+    #[jomini(default)]
+    pub diplomacy: Diplomacy,
+    #[jomini(default)]
+    pub countries: HashMap<CountryTag, ExtraCountry>,
+    #[jomini(default, duplicated)]
+    pub active_war: Vec<ExtraWar>,
+    #[jomini(default, duplicated)]
+    pub previous_war: Vec<ExtraWar>,
+    #[jomini(default)]
+    pub trade: Trade,
+    #[jomini(default)]
+    pub great_powers: GreatPowers,
+}
@@ -1,8 +1,28 @@
+mod anbennac;
+mod charts;
+mod diplomacy;
+mod dump;
+mod expression;
+mod extras;
+mod map;
+mod metrics;
+mod milestones;
+mod military;
 mod models;
+mod mods;
+mod ranking;
+mod recap;
+mod selection;
+mod serve;
+mod sqlite;
+mod standings;
+mod trade;
+mod wars;
+mod watch;
 
 use log::{error, info, trace};
 use std::cmp::max;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::env;
 use std::error::Error;
 use std::fs::File;
@@ -10,24 +30,50 @@ use std::io::{self, BufRead, BufWriter, Write};
 use std::iter::Iterator;
 use std::path::Path;
 use std::result::Result;
-use std::time::Instant;
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
 
-use eu4save::{CountryTag, Eu4Date, Eu4File, EnvTokens, query::Query, query::CountryIncomeLedger};
-use eu4save::models::{Country, GameState, Eu4Save, Province};
+use eu4save::{CountryTag, Eu4Date, Eu4File, EnvTokens, query::Query, query::CountryIncomeLedger, query::NationEvents, query::NationEventKind};
+use eu4save::models::{Country, Eu4Save, Province};
 use jomini::common::Date;
 use regex::Regex;
 
+use dump::DumpFilter;
+use extras::{ExtraCountry, Extras};
+use map::MapOptions;
+use metrics::{MetricInput, MetricRegistry};
+use military::{
+    get_army_morale, get_artillery_ca, get_cavalry_ca, get_discipline, get_force_limit, get_fort_defense,
+    get_infantry_ca, get_leader_fire, get_leader_maneuver, get_leader_shock, get_leader_siege,
+    get_merc_discipline, get_navy_force_limit, get_navy_morale, get_siege_ability, Hegemonies,
+};
+use selection::Selection;
+
 fn round_two_digits(f: f32) -> f32 {
     return (f * 100.0).round() / 100.0;
 }
 
-fn parse_save_file<P>(file_name: P) -> Result<Eu4Save, Box<dyn Error>>
+fn get_file_name<P>(file_name: P) -> String
+where P: AsRef<Path> {
+    let path = file_name.as_ref();
+    return path.file_name().unwrap_or(path.as_os_str()).to_string_lossy().to_string();
+}
+
+fn parse_save_data(data: &[u8]) -> Result<(Eu4Save, Extras), Box<dyn Error>> {
+    let file = Eu4File::from_slice(data)?;
+    let mut zip_sink = Vec::new();
+    // Parsed once, the save and the extras are both deserialized from the same parse
+    let parsed = file.parse(&mut zip_sink)?;
+    let deserializer = parsed.deserializer(&EnvTokens);
+    let eu4_save = deserializer.build_save()?;
+    let extras = deserializer.deserialize()?;
+    return Ok((eu4_save, extras));
+}
+
+fn parse_save_file<P>(file_name: P) -> Result<(Eu4Save, Extras), Box<dyn Error>>
 where P: AsRef<Path> {
     let data = std::fs::read(file_name)?;
     trace!("Bytes read: {:?}", data.len());
-
-    let file = Eu4File::from_slice(&data)?;
-    return Ok(file.parse_save(&EnvTokens)?);
+    return parse_save_data(&data);
 }
 
 fn get_avg_monarch(country: &Country, current_date: &Eu4Date) -> [f32; 3] {
@@ -122,7 +168,8 @@ fn get_income(ledger: &CountryIncomeLedger) -> f32 {
 fn generate_country_stats(
     save_query: &Query,
     country: &Country,
-    tag: &CountryTag) -> Result<models::CondensedCountry, Box<dyn Error>> {
+    tag: &CountryTag,
+    great_power: bool) -> Result<models::CondensedCountry, Box<dyn Error>> {
 
     let provinces: Vec<Province> = save_query.save()
                     .game
@@ -204,6 +251,7 @@ fn generate_country_stats(
         total_development: round_two_digits(country.raw_development),
         real_development: round_two_digits(country.development),
         gp_score: country.great_power_score.round() as i32,
+        great_power,
         powers_earned: [
             country.powers[0] + country.adm_spent_indexed.iter().map(|t| t.1).sum::<i32>(),
             country.powers[1] + country.dip_spent_indexed.iter().map(|t| t.1).sum::<i32>(),
@@ -234,369 +282,76 @@ fn generate_country_stats(
     Ok(cc)
 }
 
-fn get_discipline(country: &Country) -> f32 {
-    let mut base: f32 = 100.0;
-    // Ideas
-    for (name, amt) in &country.active_idea_groups {
-        if (name.contains("offensive") || name.contains("quality")) && *amt >= 7 {
-            base += 5.0;
-        }
-    }
-
-    // Policies
-    let policies = &country.active_policies;
-    for policy in policies {
-        if policy.policy.contains("weapon_quality") {
-            base += 5.0;
-        } else if policy.policy.contains("on_our_terms") {
-            base += 2.5;
-        }
-    }
-
-    // Advisor
-    // TBD
-
-    // Monarch
-    let events = &country.history.events;
-    let monarch_events = events.into_iter().filter(|(_k, v)| v.as_monarch().is_some());
-    let last_monarch = &monarch_events.last().unwrap().1;
-    for (personality, _) in &last_monarch.as_monarch().unwrap().personalities {
-        if personality.contains("strict") {
-            base += 5.0;
-        }
-    }
-
-    // Absolutism
-    base += f32::min(country.absolutism, 100.0) / 20.0;
-
-    return base;
-}
-
-fn get_army_morale(country: &Country) -> f32 {
-    // If you're drilling, then lol
-    let mut morale: f32 = 0.0;
-    let armies = &country.armies;
-    for army in armies {
-        let regiments = &army.regiments;
-        for r in regiments {
-            morale = morale.max(r.morale);
-        }
-    }
-    return morale;
-}
-
-fn get_force_limit(country: &Country) -> i32 {
-    // This gets the total number of troops currently, not FL
-    let mut troops: i32 = 0;
-    let armies = &country.armies;
-    for army in armies {
-        let regiments = &army.regiments;
-        troops += regiments.len() as i32;
-    }
-    return troops;
-}
-
-fn get_siege_ability(country: &Country, tag: &CountryTag, gamestate: &GameState) -> f32 {
-    let mut base: f32 = 0.0;
-    // Ideas
-    let ideas = &country.active_idea_groups;
-    for (name, amt) in ideas {
-        if name.contains("offensive") && *amt >= 5 {
-            base += 20.0;
-        } else if name.contains("espionage") && *amt >= 3 {
-            base += 10.0;
-        }
-    }
-
-    // Policies
-    let policies = &country.active_policies;
-    for policy in policies {
-        let p = &policy.policy;
-        if p.contains("word_is_my_bond") || p.contains("fear_tactics") || p.contains("siege_weapons") || p.contains("military_zeal"){
-            base += 10.0;
-        }
-    }
-
-    // War Exhaustion
-    // TBD
-
-    // Army Tradition
-    base += &country.army_tradition / 20.0;
-
-    // Army Professionalism
-    base += &country.army_professionalism / 0.05;
-
-    // Military Hegemon
-    if gamestate.military_hegemon.clone().is_some_and(|h| h.country == *tag && h.progress >= 100.0) {
-        base += 20.0;
-    }
-
-    return base;
-}
-
-fn get_fort_defense(country: &Country) -> f32 {
-    let mut base: f32 = 0.0;
-    // Ideas
-    for (name, amt) in &country.active_idea_groups {
-        if name.contains("defensive") && *amt >= 5 {
-            base += 25.0;
-        }
-    }
-
-    // Policies
-    let policies = &country.active_policies;
-    for policy in policies {
-        let p = &policy.policy;
-        if p.contains("for the people") {
-            base += 25.0;
-        } else if p.contains("privy_council") || p.contains("loyal_conduct") {
-            base += 15.0;
-        } else if p.contains("superior_fortifications") {
-            base += 10.0;
-        }
-    }
-
-    // Power Projection
-    base += &country.current_power_projection / 10.0;
-
-    return base;
-}
-
-fn get_infantry_ca(country: &Country) -> f32 {
-    let mut base: f32 = 0.0;
-    // Ideas
-    for (name, amt) in &country.active_idea_groups {
-        if name.contains("mercenary") && *amt >= 6 {
-            base += 10.0;
-        } else if name.contains("quality") && *amt >= 1 {
-            base += 10.0;
-        }
-    }
-
-    // Policies
-    let policies = &country.active_policies;
-    for policy in policies {
-        let p = &policy.policy;
-        if p.contains("modern_firearm") {
-            base += 15.0;
-        }
-    }
-
-    return base;
-}
-
-fn get_cavalry_ca(country: &Country) -> f32 {
-    let mut base: f32 = 0.0;
-    // Ideas
-    for (name, amt) in &country.active_idea_groups {
-        if name.contains("horde") && *amt >= 7 {
-            base += 25.0;
-        } else if name.contains("aristocratic") && *amt >= 1 {
-            base += 15.0;
-        } else if name.contains("quality") && *amt >= 3 {
-            base += 10.0;
-        }
-    }
-
-    // Policies
-    let policies = &country.active_policies;
-    for policy in policies {
-        let p = &policy.policy;
-        if p.contains("noble_loyalty") || p.contains("psychological") {
-            base += 10.0;
-        }
-    }
-    
-    return base;
-}
-
-fn get_artillery_ca(country: &Country) -> f32 {
-    let mut base: f32 = 0.0;
-    // Ideas
-    for (name, amt) in &country.active_idea_groups {
-        if name.contains("quality") && *amt >= 7 {
-            base += 10.0;
-        }
-    }
-
-    // Policies
-    let policies = &country.active_policies;
-    for policy in policies {
-        let p = &policy.policy;
-        if p.contains("horse_artillery") {
-            base += 10.0;
-        }
-    }
-    
-    return base;
-}
-
-fn get_leader_fire(country: &Country) -> u8 {
-    let mut base: u8 = 0;
-    // Ideas
-    for (name, amt) in &country.active_idea_groups {
-        if name.contains("offensive") && *amt >= 3 {
-            base += 1;
-        }
-    }
-
-    // Policies
-    let policies = &country.active_policies;
-    for policy in policies {
-        let p = &policy.policy;
-        if p.contains("mining_act") {
-            base += 1;
-        }
-    }
-    
-    return base;
-}
-
-fn get_leader_shock(country: &Country) -> u8 {
-    let mut base: u8 = 0;
-    // Ideas
-    for (name, amt) in &country.active_idea_groups {
-        if name.contains("offensive") && *amt >= 1 {
-            base += 1;
-        }
-    }
-
-    // Policies
-    let policies = &country.active_policies;
-    for policy in policies {
-        let p = &policy.policy;
-        if p.contains("inspirational_leaders") {
-            base += 1;
-        }
-    }
-    
-    return base;
-}
-
-fn get_leader_maneuver(country: &Country) -> u8 {
-    let mut base: u8 = 0;
-    // Ideas
-    for (name, amt) in &country.active_idea_groups {
-        if name.contains("defensive") && *amt >= 3 {
-            base += 1;
-        }
-    }
-
-    // Policies
-    let policies = &country.active_policies;
-    for policy in policies {
-        let p = &policy.policy;
-        if p.contains("hired_adventurers") {
-            base += 1;
-        }
-    }
-    
-    return base;
-}
-
-fn get_leader_siege(country: &Country) -> u8 {
-    let mut base: u8 = 0;
-    // Ideas
-    for (name, amt) in &country.active_idea_groups {
-        if name.contains("aristocratic") && *amt >= 7 {
-            base += 1;
-        }
-    }
-
-    // Policies
-    let policies = &country.active_policies;
-    for policy in policies {
-        let p = &policy.policy;
-        if p.contains("modern_siege") {
-            base += 1;
-        }
-    }
-    
-    return base;
-}
-
-fn get_navy_morale(country: &Country) -> f32 {
-    // If you're drilling, then lol
-    let mut morale: f32 = 0.0;
-    let navies = &country.navies;
-    for navy in navies {
-        let ships = &navy.ships;
-        for s in ships {
-            morale = morale.max(s.morale);
-        }
-    }
-    return morale;
-}
-
-fn get_navy_force_limit(country: &Country) -> i32 {
-    // This gets the total number of ships currently, not FL
-    let mut ships: i32 = 0;
-    let navies = &country.navies;
-    for navy in navies {
-        let s = &navy.ships;
-        ships += s.len() as i32;
-    }
-    return ships;
-}
-
-fn get_merc_discipline(country: &Country, tag: &CountryTag, gamestate: &GameState) -> f32 {
-    let mut base: f32 = 100.0;
-    // Ideas
-    for (name, amt) in &country.active_idea_groups {
-        if name.contains("mercenary") && *amt >= 7 {
-            base += 5.0;
-        }
-    }
-
-    // Policies
-    let policies = &country.active_policies;
-    for policy in policies {
-        let p = &policy.policy;
-        if p.contains("mercenary_tactical") {
-            base += 5.0;
-        }
-    }
-
-    // Economic Hegemon
-    let econ = &gamestate.economic_hegemon;
-    if econ.clone().is_some_and(|h| h.country == *tag) {
-        base += econ.clone().unwrap().progress / 10.0;
-    }
-
-    return base;
-}
-
 fn generate_military_stats(
     save_query: &Query,
     country: &Country,
     tag: &CountryTag) -> Result<models::CountryMilitary, Box<dyn Error>> {      
+    let hegemonies = Hegemonies::of(tag, &save_query.save().game);
     let military = models::CountryMilitary {
         army_tradition: round_two_digits(country.army_tradition),
-        army_morale: round_two_digits(get_army_morale(&country)),
-        army_discipline: round_two_digits(get_discipline(&country)),
-        army_force_limit: get_force_limit(&country),
+        army_morale: round_two_digits(get_army_morale(country)),
+        army_discipline: round_two_digits(get_discipline(country)),
+        army_force_limit: get_force_limit(country),
         army_professionalism: round_two_digits(country.army_professionalism * 100.0),
-        siege_ability: round_two_digits(get_siege_ability(&country, &tag, &save_query.save().game)),
-        fort_defense: round_two_digits(get_fort_defense(&country)),
-        infantry_ability: get_infantry_ca(&country),
-        cavalry_ability: get_cavalry_ca(&country),
-        artillery_ability: get_artillery_ca(&country),
+        siege_ability: round_two_digits(get_siege_ability(country, &hegemonies)),
+        fort_defense: round_two_digits(get_fort_defense(country)),
+        infantry_ability: get_infantry_ca(country),
+        cavalry_ability: get_cavalry_ca(country),
+        artillery_ability: get_artillery_ca(country),
         fire_dealt: round_two_digits(country.army_professionalism * 10.0),
         fire_received: 0.0,
         shock_dealt: round_two_digits(country.army_professionalism * 10.0),
         shock_received: 0.0,
-        leader_fire: get_leader_fire(&country), //u8
-        leader_shock: get_leader_shock(&country), //u8
-        leader_maneuver: get_leader_maneuver(&country), //u8
-        leader_siege: get_leader_siege(&country), //u8
-        mercenary_discipline: get_merc_discipline(&country, &tag, &save_query.save().game),
+        leader_fire: get_leader_fire(country), //u8
+        leader_shock: get_leader_shock(country), //u8
+        leader_maneuver: get_leader_maneuver(country), //u8
+        leader_siege: get_leader_siege(country), //u8
+        mercenary_discipline: get_merc_discipline(country, &hegemonies),
         naval_tradition: round_two_digits(country.navy_tradition),
-        naval_morale: round_two_digits(get_navy_morale(&country)),
-        naval_force_limit: get_navy_force_limit(&country),    
+        naval_morale: round_two_digits(get_navy_morale(country)),
+        naval_force_limit: get_navy_force_limit(country),    
     }; 
     Ok(military)
 }
 
+fn generate_subjects(save_query: &Query, tag: &CountryTag) -> Result<models::CountrySubjects, Box<dyn Error>> {
+    let dependencies = &save_query.save().game.diplomacy.dependencies;
+    let overlord = dependencies.iter().find(|d| d.second == *tag);
+    let subjects = models::CountrySubjects {
+        overlord: overlord.map(|d| d.first.to_string()),
+        subject_type: overlord.map(|d| d.subject_type.clone()),
+        subjects: dependencies.iter().filter(|d| d.first == *tag).map(|d| models::SubjectRelation {
+            tag: d.second.to_string(),
+            subject_type: d.subject_type.clone(),
+            start_date: d.start_date.map(|date| date.iso_8601().to_string()),
+        }).collect(),
+    };
+    Ok(subjects)
+}
+
+fn generate_bloc(save_query: &Query, country: &Country, tag: &CountryTag) -> Result<models::BlocStats, Box<dyn Error>> {
+    let ledger = save_query.country_income_breakdown(country);
+    // What the subjects pay is already part of their own income
+    let mut bloc = models::BlocStats {
+        number_countries: 1,
+        total_development: country.raw_development,
+        income: get_income(&ledger) - ledger.vassals,
+        regiments: get_force_limit(country),
+    };
+    let dependencies = &save_query.save().game.diplomacy.dependencies;
+    for dependency in dependencies.iter().filter(|d| d.first == *tag) {
+        // Subjects are summed even when the selection leaves them out of the output
+        let Some(subject) = save_query.country(&dependency.second) else { continue };
+        bloc.number_countries += 1;
+        bloc.total_development += subject.raw_development;
+        bloc.income += get_income(&save_query.country_income_breakdown(subject));
+        bloc.regiments += get_force_limit(subject);
+    }
+    bloc.total_development = round_two_digits(bloc.total_development);
+    bloc.income = round_two_digits(bloc.income);
+    Ok(bloc)
+}
+
 fn parse_localisation<P>(file_name: P) -> HashMap<String, String>
 where P: AsRef<Path>, {
     let mut localisation_map: HashMap<String, String> = HashMap::new();
@@ -663,64 +418,425 @@ fn generate_mana(country: &Country) -> Result<models::CountryMana, Box<dyn Error
     Ok(mana)
 }
 
-fn main() {
-    env_logger::init();
-    let args: Vec<String> = env::args().collect();
-    assert!(args.len() == 3);
+// The monarch of the latest monarch event in the country's history
+fn get_ruler(country: &Country) -> Option<models::RulerStats> {
+    let (date, event) = country.history.events.iter().rev().find(|(_k, v)| v.as_monarch().is_some())?;
+    let monarch = event.as_monarch().unwrap();
+    return Some(models::RulerStats {
+        name: monarch.name.clone(),
+        start_date: date.iso_8601().to_string(),
+        adm: monarch.adm,
+        dip: monarch.dip,
+        mil: monarch.mil,
+    });
+}
 
-    let localisation_file = &args[1]; // "anb_countries_l_english.yml"
-    let eu4_file_name = &args[2]; // "mp_Silverforge1663_02_06.eu4"
-    let mut stats: models::Eu4Stats = models::Eu4Stats { 
-        countries: Vec::new(),
+// Estate influence itself isn't stored in the save, only the timed modifiers to it. Crown land is
+// whatever territory the estates don't hold.
+fn generate_realm(country: &Country, extra: Option<&ExtraCountry>) -> Result<models::CountryRealm, Box<dyn Error>> {
+    let mut realm = models::CountryRealm {
+        religion: country.religion.clone(),
+        primary_culture: country.primary_culture.clone(),
+        government_rank: country.government_rank as i32,
+        crown_land: 100.0,
+        ruler: get_ruler(country),
+        ..Default::default()
     };
+    let Some(extra) = extra else { return Ok(realm) };
+    realm.accepted_cultures = extra.accepted_culture.clone();
+    realm.religious_unity = extra.religious_unity;
+    realm.tolerance_own = extra.tolerance_own;
+    realm.tolerance_heretic = extra.tolerance_heretic;
+    realm.tolerance_heathen = extra.tolerance_heathen;
+    if let Some(government) = &extra.government {
+        realm.government = Some(government.government.clone());
+        realm.government_reforms = government.reform_stack.reforms.clone();
+    }
+    for estate in &extra.estate {
+        realm.crown_land -= estate.territory;
+        realm.estates.push(models::EstateStats {
+            estate: estate.kind.clone(),
+            loyalty: round_two_digits(estate.loyalty),
+            influence_modifiers: round_two_digits(estate.influence_modifier.iter().map(|m| m.value).sum()),
+            territory: round_two_digits(estate.territory),
+        });
+    }
+    realm.crown_land = round_two_digits(realm.crown_land.max(0.0));
+    Ok(realm)
+}
 
-    let start = Instant::now();
+// The tags a nation went by, from the campaign start or from when it appeared
+fn get_tag_history(nation_events: &[NationEvents], tag: &CountryTag, start_date: &Eu4Date) -> Vec<models::TagPeriod> {
+    let Some(nation) = nation_events.iter().find(|n| n.latest == *tag) else {
+        return vec![models::TagPeriod { tag: tag.to_string(), start: Some(start_date.iso_8601().to_string()), end: None }];
+    };
+    let mut tag_history = Vec::new();
+    let mut current_tag = nation.initial;
+    let mut start = *start_date;
+    for event in &nation.events {
+        match event.kind {
+            NationEventKind::TagSwitch(t) => {
+                tag_history.push(models::TagPeriod {
+                    tag: current_tag.to_string(),
+                    start: Some(start.iso_8601().to_string()),
+                    end: Some(event.date.iso_8601().to_string()),
+                });
+                current_tag = t;
+                start = event.date;
+            }
+            NationEventKind::Appeared => start = event.date,
+            NationEventKind::Annexed => {}
+        }
+    }
+    tag_history.push(models::TagPeriod {
+        tag: current_tag.to_string(),
+        start: Some(start.iso_8601().to_string()),
+        end: None,
+    });
+    return tag_history;
+}
 
-    let localisation_map = parse_localisation(localisation_file);
-    info!("Finished parsing localisation.");
+// One entry per player/tag record of the save (`players_countries`), with the tags of the nation
+// the player holds now. The save only records the current nation of each player, so a swap to
+// another nation shows up as a change of `tag` between saves.
+fn get_player_histories(save_query: &Query, nation_events: &[NationEvents]) -> Vec<models::PlayerHistory> {
+    let start_date = &save_query.save().game.start_date;
+    let mut player_histories = Vec::new();
+    for player in save_query.players() {
+        player_histories.push(models::PlayerHistory {
+            tag: player.tag.to_string(),
+            playing: save_query.country(&player.tag).is_some_and(|c| c.human),
+            nation_tags: get_tag_history(nation_events, &player.tag, start_date),
+            name: player.name,
+        });
+    }
+    return player_histories;
+}
 
-    info!("Reading gamestate from {:?}", eu4_file_name);
-    let eu4_save = parse_save_file(eu4_file_name).unwrap();
-    let save_query = Query::from_save(eu4_save);
-    info!("Finished parsing gamestate.");
+fn generate_meta(save_query: &Query, nation_events: &[NationEvents], file_name: &str) -> Result<models::SaveMeta, Box<dyn Error>> {
+    let meta = &save_query.save().meta;
+    let version = &meta.savegame_version;
+    let save_meta = models::SaveMeta {
+        file_name: file_name.to_string(),
+        parsed_at: SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs(),
+        date: meta.date.iso_8601().to_string(),
+        game_version: format!("{}.{}.{}.{}", version.first, version.second, version.third, version.fourth),
+        checksum: meta.checksum.clone(),
+        dlc: meta.dlc_enabled.clone(),
+        mods: meta.mods_enabled_names.iter().map(|m| m.name.clone()).collect(),
+        ironman: meta.is_ironman,
+        multiplayer: meta.multi_player,
+        players: get_player_histories(save_query, nation_events),
+    };
+    Ok(save_meta)
+}
 
+fn generate_stats(
+    localisation_map: &HashMap<String, String>,
+    selection: &Selection,
+    registry: &MetricRegistry,
+    ranking_config: &models::RankingConfig,
+    file_name: &str,
+    eu4_save: Eu4Save,
+    extras: &Extras) -> Result<models::Eu4Stats, Box<dyn Error>> {
+    let save_query = Query::from_save(eu4_save);
+    let nation_events = save_query.nation_events();
+    let mut stats: models::Eu4Stats = models::Eu4Stats { 
+        schema_version: models::SCHEMA_VERSION,
+        meta: generate_meta(&save_query, &nation_events, file_name)?,
+        countries: Vec::new(),
+        diplomacy: models::DiplomacyStats::default(),
+        wars: Vec::new(),
+        trade: Vec::new(),
+        metrics: Vec::new(),
+        ranking: ranking_config.clone(),
+        existing_tags: Vec::new(),
+    };
     info!("Generating stats.");
     let players: HashMap<_, _> = save_query.players().into_iter().map(|p| (p.tag, p.name)).collect();
     info!("Players: {:?}", players);
 
+    let candidates: Vec<(CountryTag, &Country)> = save_query.countries().map(|c| (c.tag, c.country)).collect();
+    let great_powers: HashSet<CountryTag> = extras.great_powers.0.iter().copied().collect();
+    let selected = selection.select(&candidates, &players, &great_powers);
+    info!("Selected {} of {} countries", selected.len(), candidates.len());
+    stats.existing_tags = candidates.iter()
+        .filter(|(_tag, c)| c.raw_development > 0.0)
+        .map(|(tag, _c)| tag.to_string())
+        .collect();
+    stats.existing_tags.sort();
+
+    let battle_records = wars::get_battle_records(&save_query);
+    let extractors = mods::get_extractors(&stats.meta.mods);
+    stats.metrics = registry.definitions();
+
     let countries = save_query.countries();
     for c in countries {
         let country = c.country;
         let country_tag = c.tag.to_string();
         let country_name = localisation_map.get(&country_tag).unwrap_or(&country_tag).to_string();
-        if country.raw_development > 0.0 {
+        if selected.contains(&c.tag) {
             trace!("{}: {:?} {:?}", stats.countries.len(), c.id, c.tag); 
-            let country_stats = models::CountryStats {
+            let previous_tags = get_tag_history(&nation_events, &c.tag, &save_query.save().game.start_date)
+                                .into_iter()
+                                .map(|p| p.tag)
+                                .filter(|t| *t != country_tag)
+                                .collect();
+            let mut country_stats = models::CountryStats {
                 tag: country_tag,
                 name: country_name,
                 player: players.get(&c.tag).cloned(),
-                country: generate_country_stats(&save_query, &country, &c.tag).unwrap(),
-                military: generate_military_stats(&save_query, &country, &c.tag).unwrap(),
-                mana: generate_mana(&country).unwrap(),
+                previous_tags,
+                country: generate_country_stats(&save_query, &country, &c.tag, great_powers.contains(&c.tag))?,
+                military: generate_military_stats(&save_query, &country, &c.tag)?,
+                casualties: wars::generate_casualties(extras.countries.get(&c.tag), battle_records.get(&c.tag))?,
+                mana: generate_mana(&country)?,
+                realm: generate_realm(&country, extras.countries.get(&c.tag))?,
+                subjects: generate_subjects(&save_query, &c.tag)?,
+                bloc: generate_bloc(&save_query, &country, &c.tag)?,
+                trade: trade::generate_country_trade(extras, &c.tag)?,
+                anbennac: None,
+                metrics: BTreeMap::new(),
+                metric_values: BTreeMap::new(),
+                ranking: models::CountryRanking::default(),
+                standings: BTreeMap::new(),
             };
+            for extractor in &extractors {
+                extractor.extract(extras.countries.get(&c.tag), &mut country_stats);
+            }
+            country_stats.metric_values = registry.compute(&MetricInput::new(country, &country_stats));
+            country_stats.metrics = registry.unstored(&country_stats.metric_values);
             stats.countries.push(country_stats);
         }
     }
     info!("Number of countries: {}", stats.countries.len()); 
+    ranking::rank_countries(&mut stats.countries, ranking_config, &stats.metrics);
+    standings::generate_standings(&mut stats.countries, &stats.metrics);
+    stats.diplomacy = diplomacy::generate_diplomacy(&save_query, extras, &selected)?;
+    stats.wars = wars::generate_wars(&save_query, extras, &selected)?;
+    stats.trade = trade::generate_trade(extras, &selected)?;
     info!("Finished generating stats.");
 
-    let json_path = "parsed_country.json";
-    let file = File::create(json_path).unwrap();
+    Ok(stats)
+}
+
+fn write_stats<P>(stats: &models::Eu4Stats, json_path: P) -> Result<(), Box<dyn Error>>
+where P: AsRef<Path> {
+    let file = File::create(&json_path)?;
+    let mut writer = BufWriter::new(file);
+    serde_json::to_writer(&mut writer, stats)?;
+    writer.flush()?;
+    info!("Finished writing to {:?}", json_path.as_ref());
+    Ok(())
+}
+
+fn write_schema<P>(schema_path: P) -> Result<(), Box<dyn Error>>
+where P: AsRef<Path> {
+    let schema = schemars::schema_for!(models::Eu4Stats);
+    let file = File::create(&schema_path)?;
     let mut writer = BufWriter::new(file);
-    let _ = serde_json::to_writer(&mut writer, &stats);
-    let _ = match writer.flush() {
-        Ok(w) => w,
-        Err(e) => {
+    serde_json::to_writer_pretty(&mut writer, &schema)?;
+    writer.flush()?;
+    info!("Finished writing schema to {:?}", schema_path.as_ref());
+    Ok(())
+}
+
+fn main() {
+    env_logger::init();
+    let mut args: Vec<String> = env::args().collect();
+    let selection = Selection::from_args(&mut args).unwrap();
+    let registry = MetricRegistry::from_args(&mut args).unwrap();
+    let ranking_config = ranking::config_from_args(&mut args, &registry).unwrap();
+
+    if args.len() > 1 && args[1] == "watch" {
+        assert!(args.len() == 4 || args.len() == 5);
+        let localisation_map = parse_localisation(&args[2]); // "anb_countries_l_english.yml"
+        let save_dir = &args[3]; // "~/Documents/Paradox Interactive/Europa Universalis IV/save games"
+        let output_dir = args.get(4).map(|a| a.as_str()).unwrap_or("."); // "../webserver/src/assets"
+        info!("Finished parsing localisation.");
+
+        let generate = |save: &Path| {
+            info!("Reading gamestate from {:?}", save);
+            let (eu4_save, extras) = parse_save_file(save)?;
+            generate_stats(&localisation_map, &selection, &registry, &ranking_config, &get_file_name(save), eu4_save, &extras)
+        };
+        if let Err(e) = watch::watch_saves(save_dir, output_dir, generate) {
             error!("Error: {:?}", e);
-            return;
         }
-    };
-    info!("Finished writing to {:?}", json_path);
+        return;
+    }
+
+    if args.len() > 1 && args[1] == "serve" {
+        assert!(args.len() == 3 || args.len() == 4);
+        let localisation_map = parse_localisation(&args[2]); // "anb_countries_l_english.yml"
+        let address = args.get(3).map(|a| a.as_str()).unwrap_or("127.0.0.1:8080");
+        info!("Finished parsing localisation.");
+
+        let generate = |file_name: &str, data: &[u8]| {
+            let (eu4_save, extras) = parse_save_data(data)?;
+            generate_stats(&localisation_map, &selection, &registry, &ranking_config, file_name, eu4_save, &extras)
+        };
+        if let Err(e) = serve::serve(address, generate) {
+            error!("Error: {:?}", e);
+        }
+        return;
+    }
+
+    if args.len() > 1 && args[1] == "schema" {
+        assert!(args.len() == 2 || args.len() == 3);
+        let schema_path = args.get(2).map(|a| a.as_str()).unwrap_or("parsed_country.schema.json");
+        if let Err(e) = write_schema(schema_path) {
+            error!("Error: {:?}", e);
+        }
+        return;
+    }
+
+    if args.len() > 1 && args[1] == "graph" {
+        assert!(args.len() == 5);
+        let localisation_map = parse_localisation(&args[2]); // "anb_countries_l_english.yml"
+        let eu4_file_name = &args[3]; // "mp_Silverforge1663_02_06.eu4"
+        let graph_path = &args[4]; // "diplomacy.dot"
+        info!("Finished parsing localisation.");
+
+        let (eu4_save, extras) = parse_save_file(eu4_file_name).unwrap();
+        let stats = generate_stats(&localisation_map, &selection, &registry, &ranking_config, &get_file_name(eu4_file_name), eu4_save, &extras).unwrap();
+        if let Err(e) = diplomacy::write_graph(&stats, graph_path) {
+            error!("Error: {:?}", e);
+        }
+        return;
+    }
+
+    if args.len() > 1 && args[1] == "sqlite" {
+        assert!(args.len() >= 5);
+        let localisation_map = parse_localisation(&args[2]); // "anb_countries_l_english.yml"
+        let db_path = &args[3]; // "campaign.sqlite"
+        info!("Finished parsing localisation.");
+
+        for eu4_file_name in &args[4..] {
+            info!("Reading gamestate from {:?}", eu4_file_name);
+            let (eu4_save, extras) = parse_save_file(eu4_file_name).unwrap();
+            let stats = generate_stats(&localisation_map, &selection, &registry, &ranking_config, &get_file_name(eu4_file_name), eu4_save, &extras).unwrap();
+            if let Err(e) = sqlite::export_stats(db_path, &stats) {
+                error!("Error: {:?}", e);
+                return;
+            }
+        }
+        return;
+    }
+
+    if args.len() > 1 && args[1] == "charts" {
+        assert!(args.len() == 5);
+        let localisation_map = parse_localisation(&args[2]); // "anb_countries_l_english.yml"
+        let eu4_file_name = &args[3]; // "mp_Silverforge1663_02_06.eu4"
+        let output_dir = &args[4]; // "charts"
+        info!("Finished parsing localisation.");
+
+        let (eu4_save, extras) = parse_save_file(eu4_file_name).unwrap();
+        let stats = generate_stats(&localisation_map, &selection, &registry, &ranking_config, &get_file_name(eu4_file_name), eu4_save, &extras).unwrap();
+        if let Err(e) = charts::write_charts(&stats, output_dir) {
+            error!("Error: {:?}", e);
+        }
+        return;
+    }
+
+    if args.len() > 1 && args[1] == "milestones" {
+        assert!(args.len() >= 6);
+        let localisation_map = parse_localisation(&args[2]); // "anb_countries_l_english.yml"
+        let milestones_path = &args[3]; // "milestones.json"
+        info!("Finished parsing localisation.");
+
+        let mut saves = Vec::new();
+        for eu4_file_name in &args[4..] {
+            info!("Reading gamestate from {:?}", eu4_file_name);
+            let (eu4_save, extras) = parse_save_file(eu4_file_name).unwrap();
+            saves.push(generate_stats(&localisation_map, &selection, &registry, &ranking_config, &get_file_name(eu4_file_name), eu4_save, &extras).unwrap());
+        }
+        let events = milestones::detect_milestones(&saves);
+        if let Err(e) = milestones::write_milestones(&events, milestones_path) {
+            error!("Error: {:?}", e);
+        }
+        return;
+    }
+
+    if args.len() > 1 && args[1] == "recap" {
+        assert!(args.len() == 6);
+        let localisation_map = parse_localisation(&args[2]); // "anb_countries_l_english.yml"
+        let previous_file_name = &args[3]; // "mp_Silverforge1650_01_30.eu4"
+        let eu4_file_name = &args[4]; // "mp_Silverforge1663_02_06.eu4"
+        let recap_path = &args[5]; // "recap.md" or "recap.html"
+        info!("Finished parsing localisation.");
+
+        let (eu4_save, extras) = parse_save_file(previous_file_name).unwrap();
+        let previous = generate_stats(&localisation_map, &selection, &registry, &ranking_config, &get_file_name(previous_file_name), eu4_save, &extras).unwrap();
+        let (eu4_save, extras) = parse_save_file(eu4_file_name).unwrap();
+        let stats = generate_stats(&localisation_map, &selection, &registry, &ranking_config, &get_file_name(eu4_file_name), eu4_save, &extras).unwrap();
+        if let Err(e) = recap::write_recap(&stats, &previous, recap_path) {
+            error!("Error: {:?}", e);
+        }
+        return;
+    }
+
+    if args.len() > 1 && args[1] == "map" {
+        let options = MapOptions::from_args(&mut args);
+        assert!(args.len() == 6);
+        let localisation_map = parse_localisation(&args[2]); // "anb_countries_l_english.yml"
+        let eu4_file_name = &args[3]; // "mp_Silverforge1663_02_06.eu4"
+        let map_dir = &args[4]; // "~/.local/share/Steam/steamapps/workshop/content/236850/1385440355/map"
+        let map_path = &args[5]; // "map.png" or "map.svg"
+        info!("Finished parsing localisation.");
+
+        let (eu4_save, extras) = parse_save_file(eu4_file_name).unwrap();
+        let save_query = Query::from_save(eu4_save);
+        let players: HashMap<_, _> = save_query.players().into_iter().map(|p| (p.tag, p.name)).collect();
+        let data = map::get_map_data(&save_query, &extras, &localisation_map, &players);
+        if let Err(e) = map::write_map(&data, &options, map_dir, map_path) {
+            error!("Error: {:?}", e);
+        }
+        return;
+    }
+
+    if args.len() > 1 && args[1] == "dump" {
+        let filter = DumpFilter::from_args(&mut args).unwrap();
+        assert!(args.len() == 4 || args.len() == 5);
+        let localisation_map = parse_localisation(&args[2]); // "anb_countries_l_english.yml"
+        let eu4_file_name = &args[3]; // "mp_Silverforge1663_02_06.eu4"
+        let dump_path = args.get(4).map(|a| a.as_str()); // "flags.json"
+        info!("Finished parsing localisation.");
+
+        let (eu4_save, extras) = parse_save_file(eu4_file_name).unwrap();
+        let save_query = Query::from_save(eu4_save);
+        let players: HashMap<_, _> = save_query.players().into_iter().map(|p| (p.tag, p.name)).collect();
+        let candidates: Vec<(CountryTag, &Country)> = save_query.countries().map(|c| (c.tag, c.country)).collect();
+        let great_powers: HashSet<CountryTag> = extras.great_powers.0.iter().copied().collect();
+        let selected = selection.select(&candidates, &players, &great_powers);
+        let dumps = dump::generate_dump(&save_query, &extras, &localisation_map, &players, &selected, &filter).unwrap();
+        if let Err(e) = dump::write_dump(&dumps, dump_path) {
+            error!("Error: {:?}", e);
+        }
+        return;
+    }
+
+    assert!(args.len() == 3);
+
+    let localisation_file = &args[1]; // "anb_countries_l_english.yml"
+    let eu4_file_name = &args[2]; // "mp_Silverforge1663_02_06.eu4"
+
+    let start = Instant::now();
+
+    let localisation_map = parse_localisation(localisation_file);
+    info!("Finished parsing localisation.");
+
+    info!("Reading gamestate from {:?}", eu4_file_name);
+    let (eu4_save, extras) = parse_save_file(eu4_file_name).unwrap();
+    info!("Finished parsing gamestate.");
+
+    let stats = generate_stats(&localisation_map, &selection, &registry, &ranking_config, &get_file_name(eu4_file_name), eu4_save, &extras).unwrap();
+
+    let json_path = "parsed_country.json";
+    if let Err(e) = write_stats(&stats, json_path) {
+        error!("Error: {:?}", e);
+        return;
+    }
 
     let duration = start.elapsed();
     info!("Time spent parsing: {:?}", duration);
@@ -0,0 +1,447 @@
+// A political map of the save, drawn onto the provinces.bmp of the game or mod. Every province in
+// the bitmap has its own colour, which definition.csv maps to a province id. Each pixel is then
+// painted in the map colour of the province's owner, or by development for a heatmap, and country
+// borders are drawn where neighbouring pixels have different owners.
+use log::info;
+use std::collections::HashMap;
+use std::error::Error;
+use std::fs;
+use std::io::Write;
+use std::path::Path;
+use std::result::Result;
+
+use eu4save::CountryTag;
+use eu4save::query::Query;
+
+use crate::extras::Extras;
+use crate::recap::escape;
+
+// Sea, wasteland and uncolonised land
+const UNOWNED: [u8; 3] = [0xd8, 0xd0, 0xc0];
+// Countries that aren't highlighted
+const OTHER: [u8; 3] = [0xb4, 0xb4, 0xb4];
+const BORDER: [u8; 3] = [0x28, 0x28, 0x28];
+const HEAT_LOW: [u8; 3] = [0xff, 0xf5, 0xc0];
+const HEAT_HIGH: [u8; 3] = [0xb0, 0x10, 0x10];
+// Stops a broken overlord chain from looping
+const MAX_OVERLORDS: usize = 10;
+
+// RGB pixels from the top left, row by row
+#[derive(Debug, Clone, PartialEq)]
+pub struct Bitmap {
+    pub width: usize,
+    pub height: usize,
+    pub pixels: Vec<[u8; 3]>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct MapProvince {
+    pub owner: Option<String>,
+    pub development: f32,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct MapCountry {
+    pub name: String,
+    pub color: [u8; 3],
+    pub overlord: Option<String>,
+    pub player: Option<String>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct MapData {
+    pub provinces: HashMap<u16, MapProvince>,
+    pub countries: HashMap<String, MapCountry>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct MapOptions {
+    // Everyone but the players in grey
+    pub highlight_players: bool,
+    // Subjects in the colour of their top overlord
+    pub subjects_as_overlord: bool,
+    // Provinces coloured by development instead of owner
+    pub development: bool,
+}
+
+impl MapOptions {
+    // Removes the map flags from `args`, like `Selection::from_args`
+    pub fn from_args(args: &mut Vec<String>) -> MapOptions {
+        let mut options = MapOptions::default();
+        let mut i = 0;
+        while i < args.len() {
+            match args[i].as_str() {
+                "--highlight-players" => options.highlight_players = true,
+                "--subjects-as-overlord" => options.subjects_as_overlord = true,
+                "--development" => options.development = true,
+                _ => {
+                    i += 1;
+                    continue;
+                }
+            }
+            args.remove(i);
+        }
+        return options;
+    }
+}
+
+fn read_u16(data: &[u8], offset: usize) -> Result<u16, Box<dyn Error>> {
+    let bytes = data.get(offset..offset + 2).ok_or("Truncated bitmap")?;
+    return Ok(u16::from_le_bytes([bytes[0], bytes[1]]));
+}
+
+fn read_u32(data: &[u8], offset: usize) -> Result<u32, Box<dyn Error>> {
+    let bytes = data.get(offset..offset + 4).ok_or("Truncated bitmap")?;
+    return Ok(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]));
+}
+
+// Uncompressed 24 or 32 bit BMP, which is what provinces.bmp is
+pub fn read_bmp(data: &[u8]) -> Result<Bitmap, Box<dyn Error>> {
+    if !data.starts_with(b"BM") {
+        return Err("Not a BMP file".into());
+    }
+    let offset = read_u32(data, 10)? as usize;
+    let width = read_u32(data, 18)? as i32;
+    let height = read_u32(data, 22)? as i32;
+    let bits = read_u16(data, 28)?;
+    let compression = read_u32(data, 30)?;
+    if (bits != 24 && bits != 32) || compression != 0 || width <= 0 || height == 0 {
+        return Err(format!("Unsupported BMP: {} bits, compression {}, {}x{}", bits, compression, width, height).into());
+    }
+    let (width, bottom_up, height) = (width as usize, height > 0, height.unsigned_abs() as usize);
+    let bytes_per_pixel = bits as usize / 8;
+    let stride = (width * bytes_per_pixel + 3) & !3;
+
+    let mut pixels = Vec::with_capacity(width * height);
+    for y in 0..height {
+        let row = if bottom_up { height - 1 - y } else { y };
+        let start = offset + row * stride;
+        let bytes = data.get(start..start + width * bytes_per_pixel).ok_or("Truncated bitmap")?;
+        // Stored as blue, green, red
+        pixels.extend(bytes.chunks(bytes_per_pixel).map(|p| [p[2], p[1], p[0]]));
+    }
+    return Ok(Bitmap { width, height, pixels });
+}
+
+// definition.csv lines look like `1;128;34;64;Stockholm;x`. The header and anything else that
+// isn't a province is skipped.
+pub fn read_definitions(data: &[u8]) -> HashMap<[u8; 3], u16> {
+    let mut definitions = HashMap::new();
+    for line in String::from_utf8_lossy(data).lines() {
+        let fields: Vec<&str> = line.split(';').map(|f| f.trim()).collect();
+        if fields.len() < 4 {
+            continue;
+        }
+        let (Ok(id), Ok(r), Ok(g), Ok(b)) = (fields[0].parse(), fields[1].parse(), fields[2].parse(), fields[3].parse()) else {
+            continue;
+        };
+        definitions.insert([r, g, b], id);
+    }
+    return definitions;
+}
+
+// Owners, development and country colours from the save
+pub fn get_map_data(
+    save_query: &Query,
+    extras: &Extras,
+    localisation_map: &HashMap<String, String>,
+    players: &HashMap<CountryTag, String>) -> MapData {
+
+    let mut data = MapData::default();
+    for (id, province) in &save_query.save().game.provinces {
+        data.provinces.insert(id.as_u16(), MapProvince {
+            owner: province.owner.map(|o| o.to_string()),
+            development: province.base_tax + province.base_production + province.base_manpower,
+        });
+    }
+    for c in save_query.countries() {
+        let tag = c.tag.to_string();
+        let color = extras.countries.get(&c.tag)
+            .and_then(|e| e.colors.as_ref())
+            .and_then(|colors| colors.map_color.get(0..3))
+            .map(|rgb| [rgb[0], rgb[1], rgb[2]])
+            .unwrap_or(OTHER);
+        data.countries.insert(tag.clone(), MapCountry {
+            name: localisation_map.get(&tag).unwrap_or(&tag).to_string(),
+            color,
+            overlord: c.country.overlord.map(|o| o.to_string()),
+            player: players.get(&c.tag).cloned(),
+        });
+    }
+    return data;
+}
+
+fn get_top_overlord<'a>(data: &'a MapData, tag: &'a str) -> &'a str {
+    let mut current = tag;
+    for _ in 0..MAX_OVERLORDS {
+        match data.countries.get(current).and_then(|c| c.overlord.as_deref()) {
+            Some(overlord) => current = overlord,
+            None => break,
+        }
+    }
+    return current;
+}
+
+fn get_heat(share: f32) -> [u8; 3] {
+    let share = share.clamp(0.0, 1.0);
+    let mix = |i: usize| (HEAT_LOW[i] as f32 + (HEAT_HIGH[i] as f32 - HEAT_LOW[i] as f32) * share).round() as u8;
+    return [mix(0), mix(1), mix(2)];
+}
+
+// The country a province counts for, after the options, or `None` when it's unowned
+fn get_shown_owner<'a>(data: &'a MapData, options: &MapOptions, province: &'a MapProvince) -> Option<&'a str> {
+    let owner = province.owner.as_deref()?;
+    if options.subjects_as_overlord {
+        return Some(get_top_overlord(data, owner));
+    }
+    return Some(owner);
+}
+
+fn get_province_color(data: &MapData, options: &MapOptions, max_development: f32, province: &MapProvince) -> [u8; 3] {
+    let Some(owner) = get_shown_owner(data, options, province) else {
+        return UNOWNED;
+    };
+    let country = data.countries.get(owner);
+    if options.highlight_players && country.and_then(|c| c.player.as_ref()).is_none() {
+        return OTHER;
+    }
+    if options.development {
+        return get_heat(province.development / max_development);
+    }
+    return country.map(|c| c.color).unwrap_or(OTHER);
+}
+
+// Paints `provinces` (the provinces.bmp) with `data`
+pub fn render_map(provinces: &Bitmap, definitions: &HashMap<[u8; 3], u16>, data: &MapData, options: &MapOptions) -> Bitmap {
+    let max_development = data.provinces.values()
+        .filter(|p| p.owner.is_some())
+        .map(|p| p.development)
+        .fold(1.0f32, f32::max);
+
+    // For each province colour the colour to paint and the owner to draw borders around
+    let mut owners: HashMap<&str, usize> = HashMap::new();
+    let mut styles: HashMap<[u8; 3], ([u8; 3], Option<usize>)> = HashMap::new();
+    for (key, id) in definitions {
+        let Some(province) = data.provinces.get(id) else { continue };
+        let owner = get_shown_owner(data, options, province).map(|o| {
+            let next = owners.len();
+            *owners.entry(o).or_insert(next)
+        });
+        styles.insert(*key, (get_province_color(data, options, max_development, province), owner));
+    }
+
+    let styled: Vec<([u8; 3], Option<usize>)> = provinces.pixels.iter()
+        .map(|p| styles.get(p).copied().unwrap_or((UNOWNED, None)))
+        .collect();
+    let (width, height) = (provinces.width, provinces.height);
+    let mut pixels: Vec<[u8; 3]> = styled.iter().map(|(color, _owner)| *color).collect();
+    for y in 0..height {
+        for x in 0..width {
+            let i = y * width + x;
+            let owner = styled[i].1;
+            let right = if x + 1 < width { styled[i + 1].1 } else { owner };
+            let below = if y + 1 < height { styled[i + width].1 } else { owner };
+            if owner.is_some() && (right != owner || below != owner) {
+                pixels[i] = BORDER;
+            }
+        }
+    }
+    return Bitmap { width, height, pixels };
+}
+
+// Players, each in the colour their provinces are painted
+pub fn get_legend(data: &MapData) -> Vec<(String, [u8; 3])> {
+    let mut legend: Vec<(String, [u8; 3])> = data.countries.values()
+        .filter_map(|c| c.player.as_ref().map(|p| (format!("{} ({})", c.name, p), c.color)))
+        .collect();
+    legend.sort();
+    return legend;
+}
+
+pub fn encode_png(bitmap: &Bitmap) -> Result<Vec<u8>, Box<dyn Error>> {
+    let mut png_data = Vec::new();
+    let mut encoder = png::Encoder::new(&mut png_data, bitmap.width as u32, bitmap.height as u32);
+    encoder.set_color(png::ColorType::Rgb);
+    encoder.set_depth(png::BitDepth::Eight);
+    let mut writer = encoder.write_header()?;
+    writer.write_image_data(&bitmap.pixels.concat())?;
+    writer.finish()?;
+    return Ok(png_data);
+}
+
+fn encode_base64(data: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut encoded = String::with_capacity(data.len().div_ceil(3) * 4);
+    for chunk in data.chunks(3) {
+        let bytes = [chunk[0], *chunk.get(1).unwrap_or(&0), *chunk.get(2).unwrap_or(&0)];
+        let triple = (bytes[0] as u32) << 16 | (bytes[1] as u32) << 8 | bytes[2] as u32;
+        for i in 0..4 {
+            if i <= chunk.len() {
+                encoded.push(ALPHABET[(triple >> (18 - 6 * i) & 0x3f) as usize] as char);
+            } else {
+                encoded.push('=');
+            }
+        }
+    }
+    return encoded;
+}
+
+// The map as an embedded PNG, with the legend as text on top
+pub fn encode_svg(bitmap: &Bitmap, legend: &[(String, [u8; 3])]) -> Result<String, Box<dyn Error>> {
+    let (width, height) = (bitmap.width, bitmap.height);
+    let mut svg = format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{}\" height=\"{}\" viewBox=\"0 0 {} {}\" font-family=\"sans-serif\" font-size=\"14\">\n",
+        width, height, width, height);
+    svg += &format!("<image width=\"{}\" height=\"{}\" image-rendering=\"pixelated\" href=\"data:image/png;base64,{}\"/>\n", width, height, encode_base64(&encode_png(bitmap)?));
+    if !legend.is_empty() {
+        let top = height as f32 - 12.0 - legend.len() as f32 * 20.0;
+        svg += &format!("<rect x=\"8\" y=\"{}\" width=\"240\" height=\"{}\" fill=\"white\" fill-opacity=\"0.8\"/>\n", top - 8.0, legend.len() as f32 * 20.0 + 12.0);
+        for (i, (label, color)) in legend.iter().enumerate() {
+            let y = top + i as f32 * 20.0;
+            svg += &format!("<rect x=\"16\" y=\"{}\" width=\"14\" height=\"14\" fill=\"#{:02x}{:02x}{:02x}\" stroke=\"black\"/>\n", y, color[0], color[1], color[2]);
+            svg += &format!("<text x=\"38\" y=\"{}\">{}</text>\n", y + 12.0, escape(label));
+        }
+    }
+    svg += "</svg>\n";
+    return Ok(svg);
+}
+
+// Reads provinces.bmp and definition.csv from `map_dir`, the map folder of the game or mod, and
+// writes an SVG when `map_path` ends in .svg and a PNG otherwise
+pub fn write_map<P, Q>(data: &MapData, options: &MapOptions, map_dir: P, map_path: Q) -> Result<(), Box<dyn Error>>
+where P: AsRef<Path>, Q: AsRef<Path> {
+    let map_dir = map_dir.as_ref();
+    let provinces = read_bmp(&fs::read(map_dir.join("provinces.bmp"))?)?;
+    let definitions = read_definitions(&fs::read(map_dir.join("definition.csv"))?);
+    info!("Read {}x{} map with {} provinces", provinces.width, provinces.height, definitions.len());
+
+    let map = render_map(&provinces, &definitions, data, options);
+    let map_path = map_path.as_ref();
+    let mut file = fs::File::create(map_path)?;
+    if map_path.extension().is_some_and(|e| e == "svg") {
+        let legend = if options.development { Vec::new() } else { get_legend(data) };
+        file.write_all(encode_svg(&map, &legend)?.as_bytes())?;
+    } else {
+        file.write_all(&encode_png(&map)?)?;
+    }
+    info!("Finished writing map to {:?}", map_path);
+    return Ok(());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // tests/fixtures/map is an 8x4 map written by tests/fixtures/generate.py:
+    //
+    //     1 1 2 2 3 3 5 5
+    //     1 1 2 2 3 3 5 5
+    //     4 4 4 4 3 3 5 5
+    //     4 4 4 4 5 5 5 5
+    //
+    // with province 5 as the sea
+    fn read_fixture() -> (Bitmap, HashMap<[u8; 3], u16>) {
+        let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests").join("fixtures").join("map");
+        let provinces = read_bmp(&fs::read(dir.join("provinces.bmp")).unwrap()).unwrap();
+        let definitions = read_definitions(&fs::read(dir.join("definition.csv")).unwrap());
+        return (provinces, definitions);
+    }
+
+    fn country(color: [u8; 3], overlord: Option<&str>, player: Option<&str>) -> MapCountry {
+        return MapCountry {
+            name: color[0].to_string(),
+            color,
+            overlord: overlord.map(|o| o.to_string()),
+            player: player.map(|p| p.to_string()),
+        };
+    }
+
+    // A01 (a player) owns 1 and 2, its subject B01 owns 3 and Z01 owns 4
+    fn data() -> MapData {
+        let province = |owner: Option<&str>, development: f32| MapProvince { owner: owner.map(|o| o.to_string()), development };
+        return MapData {
+            provinces: HashMap::from([
+                (1, province(Some("A01"), 10.0)),
+                (2, province(Some("A01"), 20.0)),
+                (3, province(Some("B01"), 5.0)),
+                (4, province(Some("Z01"), 40.0)),
+                (5, province(None, 0.0)),
+            ]),
+            countries: HashMap::from([
+                ("A01".to_string(), country([200, 0, 0], None, Some("Alice"))),
+                ("B01".to_string(), country([0, 200, 0], Some("A01"), None)),
+                ("Z01".to_string(), country([0, 0, 200], None, None)),
+            ]),
+        };
+    }
+
+    fn pixel(map: &Bitmap, x: usize, y: usize) -> [u8; 3] {
+        return map.pixels[y * map.width + x];
+    }
+
+    #[test]
+    fn reads_fixture() {
+        let (provinces, definitions) = read_fixture();
+        assert_eq!((provinces.width, provinces.height), (8, 4));
+        assert_eq!(definitions.len(), 5);
+        assert_eq!(definitions.get(&pixel(&provinces, 0, 0)), Some(&1));
+        assert_eq!(definitions.get(&pixel(&provinces, 7, 3)), Some(&5));
+        assert_eq!(definitions.get(&pixel(&provinces, 0, 3)), Some(&4));
+    }
+
+    #[test]
+    fn owner_colors_and_borders() {
+        let (provinces, definitions) = read_fixture();
+        let map = render_map(&provinces, &definitions, &data(), &MapOptions::default());
+        assert_eq!(pixel(&map, 0, 0), [200, 0, 0]);
+        // Provinces 1 and 2 have the same owner, so no border between them
+        assert_eq!(pixel(&map, 1, 0), [200, 0, 0]);
+        assert_eq!(pixel(&map, 3, 0), BORDER);
+        assert_eq!(pixel(&map, 4, 0), [0, 200, 0]);
+        assert_eq!(pixel(&map, 0, 3), [0, 0, 200]);
+        assert_eq!(pixel(&map, 7, 3), UNOWNED);
+    }
+
+    #[test]
+    fn subjects_as_overlord() {
+        let (provinces, definitions) = read_fixture();
+        let options = MapOptions { subjects_as_overlord: true, ..Default::default() };
+        let map = render_map(&provinces, &definitions, &data(), &options);
+        assert_eq!(pixel(&map, 4, 0), [200, 0, 0]);
+        // B01 is now part of A01, so the border moved to the sea
+        assert_eq!(pixel(&map, 3, 0), [200, 0, 0]);
+        assert_eq!(pixel(&map, 5, 0), BORDER);
+    }
+
+    #[test]
+    fn highlight_players() {
+        let (provinces, definitions) = read_fixture();
+        let options = MapOptions { highlight_players: true, ..Default::default() };
+        let map = render_map(&provinces, &definitions, &data(), &options);
+        assert_eq!(pixel(&map, 0, 0), [200, 0, 0]);
+        assert_eq!(pixel(&map, 4, 0), OTHER);
+        assert_eq!(pixel(&map, 0, 3), OTHER);
+
+        let options = MapOptions { highlight_players: true, subjects_as_overlord: true, ..Default::default() };
+        let map = render_map(&provinces, &definitions, &data(), &options);
+        assert_eq!(pixel(&map, 4, 0), [200, 0, 0]);
+    }
+
+    #[test]
+    fn development_heatmap() {
+        let (provinces, definitions) = read_fixture();
+        let options = MapOptions { development: true, ..Default::default() };
+        let map = render_map(&provinces, &definitions, &data(), &options);
+        assert_eq!(pixel(&map, 0, 3), HEAT_HIGH);
+        assert_eq!(pixel(&map, 0, 0), get_heat(0.25));
+        assert_eq!(pixel(&map, 7, 0), UNOWNED);
+    }
+
+    #[test]
+    fn base64() {
+        assert_eq!(encode_base64(b""), "");
+        assert_eq!(encode_base64(b"f"), "Zg==");
+        assert_eq!(encode_base64(b"fo"), "Zm8=");
+        assert_eq!(encode_base64(b"foobar"), "Zm9vYmFy");
+    }
+}
@@ -0,0 +1,596 @@
+// Every numeric stat is also a metric: a key, a category, a unit and a function computing it for a
+// country. The output lists the definitions once, so a frontend can show any metric without knowing
+// about it in advance. The registry describes the stats, it doesn't hold them: the fields stay in
+// the models and each has a definition in `default_metrics` naming it as its `path`, where its
+// value is written. The other metrics, such as sums of fields, values read straight from the save
+// and user-defined metrics, are written to each country's `metrics` map. So a metric derived from
+// the existing stats is one entry in `default_metrics`, and users can add their own as expressions
+// in a config file, see `load_metrics`, while a new field is added to the models and described here.
+use log::{error, info, warn};
+use std::cell::OnceCell;
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fs::File;
+use std::io::BufReader;
+use std::path::Path;
+use std::result::Result;
+
+use eu4save::models::Country;
+use serde::Deserialize;
+use serde_json::Value;
+
+use crate::expression::Expression;
+use crate::models::{CountryStats, MetricDefinition};
+
+pub const DEVELOPMENT: &str = "development";
+pub const DUCATS: &str = "ducats";
+pub const MEN: &str = "men";
+pub const MANA: &str = "mana";
+pub const PERCENT: &str = "percent";
+pub const COUNT: &str = "count";
+pub const LEVEL: &str = "level";
+pub const SCORE: &str = "score";
+
+// What a metric can read: the country from the save and the stats already generated for it, also
+// as JSON for the fields of expressions
+pub struct MetricInput<'a> {
+    pub country: &'a Country,
+    pub stats: &'a CountryStats,
+    serialized: OnceCell<Option<Value>>,
+}
+
+impl<'a> MetricInput<'a> {
+    pub fn new(country: &'a Country, stats: &'a CountryStats) -> MetricInput<'a> {
+        return MetricInput {
+            country,
+            stats,
+            serialized: OnceCell::new(),
+        };
+    }
+
+    // Serialized the first time an expression asks for it, once per country
+    pub fn serialized(&self) -> Option<&Value> {
+        return self.serialized.get_or_init(|| match serde_json::to_value(self.stats) {
+            Ok(value) => Some(value),
+            Err(e) => {
+                error!("Could not serialize the stats of {}: {:?}", self.stats.tag, e);
+                None
+            }
+        }).as_ref();
+    }
+}
+
+pub trait Metric {
+    // Unique, such as `military.army_discipline`
+    fn key(&self) -> &str;
+    fn category(&self) -> &str;
+    fn label(&self) -> &str;
+    fn unit(&self) -> &str;
+    // Where the value is stored in the country's stats, such as `country.technology[0]`. `None`
+    // for metrics written to the `metrics` map.
+    fn path(&self) -> Option<&str> {
+        return None;
+    }
+    // For losses, inflation and the like, so the lowest value ranks first
+    fn lower_is_better(&self) -> bool {
+        return false;
+    }
+    // The fields of the stats it reads, checked when the metric is registered
+    fn fields(&self) -> Vec<&str> {
+        return Vec::new();
+    }
+    // `None` leaves the metric out for this country. `computed` holds the metrics registered
+    // before this one.
+    fn compute(&self, input: &MetricInput, computed: &BTreeMap<String, f32>) -> Option<f32>;
+}
+
+pub struct FieldMetric {
+    key: String,
+    category: &'static str,
+    label: &'static str,
+    unit: &'static str,
+    path: Option<String>,
+    lower_is_better: bool,
+    compute: fn(&MetricInput) -> Option<f32>,
+}
+
+impl FieldMetric {
+    // Stored under another name than its key
+    fn at(mut self, path: &str) -> FieldMetric {
+        self.path = Some(path.to_string());
+        return self;
+    }
+
+    // Not stored in the stats, so written to the `metrics` map
+    fn derived(mut self) -> FieldMetric {
+        self.path = None;
+        return self;
+    }
+
+    fn less_is_better(mut self) -> FieldMetric {
+        self.lower_is_better = true;
+        return self;
+    }
+}
+
+impl Metric for FieldMetric {
+    fn key(&self) -> &str {
+        return &self.key;
+    }
+
+    fn category(&self) -> &str {
+        return self.category;
+    }
+
+    fn label(&self) -> &str {
+        return self.label;
+    }
+
+    fn unit(&self) -> &str {
+        return self.unit;
+    }
+
+    fn path(&self) -> Option<&str> {
+        return self.path.as_deref();
+    }
+
+    fn lower_is_better(&self) -> bool {
+        return self.lower_is_better;
+    }
+
+    fn compute(&self, input: &MetricInput, _computed: &BTreeMap<String, f32>) -> Option<f32> {
+        return (self.compute)(input);
+    }
+}
+
+// A field of the stats, stored at its key unless `at` or `derived` say otherwise
+fn field(category: &'static str, name: &str, label: &'static str, unit: &'static str, compute: fn(&MetricInput) -> Option<f32>) -> FieldMetric {
+    let key = format!("{}.{}", category, name);
+    return FieldMetric {
+        path: Some(key.clone()),
+        key,
+        category,
+        label,
+        unit,
+        lower_is_better: false,
+        compute,
+    };
+}
+
+fn default_metrics() -> Vec<Box<dyn Metric>> {
+    let metrics = vec![
+        field("country", "total_development", "Total development", DEVELOPMENT, |m| Some(m.stats.country.total_development)),
+        field("country", "real_development", "Real development", DEVELOPMENT, |m| Some(m.stats.country.real_development)),
+        field("country", "average_development", "Average development", DEVELOPMENT, |m| Some(m.stats.country.average_development)),
+        field("country", "average_development_real", "Average real development", DEVELOPMENT, |m| Some(m.stats.country.average_development_real)),
+        field("country", "gp_score", "Great power score", SCORE, |m| Some(m.stats.country.gp_score as f32)),
+        field("country", "adm_earned", "Admin power earned", MANA, |m| Some(m.stats.country.powers_earned[0] as f32)).at("country.powers_earned[0]"),
+        field("country", "dip_earned", "Diplomatic power earned", MANA, |m| Some(m.stats.country.powers_earned[1] as f32)).at("country.powers_earned[1]"),
+        field("country", "mil_earned", "Military power earned", MANA, |m| Some(m.stats.country.powers_earned[2] as f32)).at("country.powers_earned[2]"),
+        field("country", "adm_tech", "Admin tech", LEVEL, |m| Some(m.stats.country.technology[0] as f32)).at("country.technology[0]"),
+        field("country", "dip_tech", "Diplomatic tech", LEVEL, |m| Some(m.stats.country.technology[1] as f32)).at("country.technology[1]"),
+        field("country", "mil_tech", "Military tech", LEVEL, |m| Some(m.stats.country.technology[2] as f32)).at("country.technology[2]"),
+        field("country", "total_ideas", "Ideas", COUNT, |m| Some(m.stats.country.total_ideas as f32)),
+        // The first idea group is the national one
+        field("country", "full_idea_groups", "Full idea groups", COUNT, |m| Some(m.stats.country.ideas.iter().skip(1).filter(|(_name, level)| *level >= 7).count() as f32)).derived(),
+        field("country", "current_manpower", "Manpower", MEN, |m| Some(m.stats.country.current_manpower as f32)),
+        field("country", "max_manpower", "Max manpower", MEN, |m| Some(m.stats.country.max_manpower as f32)),
+        field("country", "average_monarch_adm", "Average monarch admin", LEVEL, |m| Some(m.stats.country.average_monarch[0])).at("country.average_monarch[0]"),
+        field("country", "average_monarch_dip", "Average monarch diplomatic", LEVEL, |m| Some(m.stats.country.average_monarch[1])).at("country.average_monarch[1]"),
+        field("country", "average_monarch_mil", "Average monarch military", LEVEL, |m| Some(m.stats.country.average_monarch[2])).at("country.average_monarch[2]"),
+        field("country", "income", "Monthly income", DUCATS, |m| Some(m.stats.country.income)),
+        field("country", "treasury", "Treasury", DUCATS, |m| Some(m.country.treasury)).derived(),
+        field("country", "inflation", "Inflation", PERCENT, |m| Some(m.country.inflation)).derived().less_is_better(),
+        field("country", "number_provinces", "Provinces", COUNT, |m| Some(m.stats.country.number_provinces as f32)),
+        field("country", "number_buildings", "Buildings", COUNT, |m| Some(m.stats.country.number_buildings as f32)),
+        field("country", "buildings_value", "Buildings value", DUCATS, |m| Some(m.stats.country.buildings_value as f32)),
+        field("country", "buildings_per_province", "Buildings per province", COUNT, |m| Some(m.stats.country.buildings_per_province)),
+        field("country", "innovativeness", "Innovativeness", SCORE, |m| Some(m.stats.country.innovativeness)),
+        field("country", "absolutism", "Absolutism", SCORE, |m| Some(m.stats.country.absolutism)),
+
+        field("military", "army_tradition", "Army tradition", PERCENT, |m| Some(m.stats.military.army_tradition)),
+        field("military", "army_morale", "Army morale", SCORE, |m| Some(m.stats.military.army_morale)),
+        field("military", "army_discipline", "Discipline", PERCENT, |m| Some(m.stats.military.army_discipline)),
+        field("military", "army_force_limit", "Regiments", COUNT, |m| Some(m.stats.military.army_force_limit as f32)),
+        field("military", "army_professionalism", "Army professionalism", PERCENT, |m| Some(m.stats.military.army_professionalism)),
+        field("military", "siege_ability", "Siege ability", PERCENT, |m| Some(m.stats.military.siege_ability)),
+        field("military", "fort_defense", "Fort defense", PERCENT, |m| Some(m.stats.military.fort_defense)),
+        field("military", "infantry_ability", "Infantry combat ability", PERCENT, |m| Some(m.stats.military.infantry_ability)),
+        field("military", "cavalry_ability", "Cavalry combat ability", PERCENT, |m| Some(m.stats.military.cavalry_ability)),
+        field("military", "artillery_ability", "Artillery combat ability", PERCENT, |m| Some(m.stats.military.artillery_ability)),
+        field("military", "fire_dealt", "Fire damage dealt", PERCENT, |m| Some(m.stats.military.fire_dealt)),
+        field("military", "fire_received", "Fire damage received", PERCENT, |m| Some(m.stats.military.fire_received)).less_is_better(),
+        field("military", "shock_dealt", "Shock damage dealt", PERCENT, |m| Some(m.stats.military.shock_dealt)),
+        field("military", "shock_received", "Shock damage received", PERCENT, |m| Some(m.stats.military.shock_received)).less_is_better(),
+        field("military", "leader_fire", "Leader fire", LEVEL, |m| Some(m.stats.military.leader_fire as f32)),
+        field("military", "leader_shock", "Leader shock", LEVEL, |m| Some(m.stats.military.leader_shock as f32)),
+        field("military", "leader_maneuver", "Leader maneuver", LEVEL, |m| Some(m.stats.military.leader_maneuver as f32)),
+        field("military", "leader_siege", "Leader siege", LEVEL, |m| Some(m.stats.military.leader_siege as f32)),
+        field("military", "mercenary_discipline", "Mercenary discipline", PERCENT, |m| Some(m.stats.military.mercenary_discipline)),
+        field("military", "naval_tradition", "Navy tradition", PERCENT, |m| Some(m.stats.military.naval_tradition)),
+        field("military", "naval_morale", "Naval morale", SCORE, |m| Some(m.stats.military.naval_morale)),
+        field("military", "naval_force_limit", "Ships", COUNT, |m| Some(m.stats.military.naval_force_limit as f32)),
+
+        field("casualties", "total_land_losses", "Land losses", MEN, |m| Some(m.stats.casualties.total_land_losses as f32)).less_is_better(),
+        field("casualties", "ships_lost", "Ships lost", COUNT, |m| Some(m.stats.casualties.ships_lost as f32)).less_is_better(),
+        field("casualties", "battles_won", "Battles won", COUNT, |m| Some(m.stats.casualties.battles_won as f32)),
+        field("casualties", "battles_lost", "Battles lost", COUNT, |m| Some(m.stats.casualties.battles_lost as f32)).less_is_better(),
+
+        field("mana", "adm_spent", "Admin power spent", MANA, |m| Some(m.stats.mana.mana_spent[0] as f32)).at("mana.mana_spent[0]"),
+        field("mana", "dip_spent", "Diplomatic power spent", MANA, |m| Some(m.stats.mana.mana_spent[1] as f32)).at("mana.mana_spent[1]"),
+        field("mana", "mil_spent", "Military power spent", MANA, |m| Some(m.stats.mana.mana_spent[2] as f32)).at("mana.mana_spent[2]"),
+        field("mana", "spent_developing", "Spent developing", MANA, |m| Some(m.stats.mana.spent_developing.iter().sum::<i32>() as f32)).derived(),
+        field("mana", "spent_tech", "Spent on technology", MANA, |m| Some(m.stats.mana.spent_tech as f32)),
+        field("mana", "spent_culture", "Spent on culture", MANA, |m| Some(m.stats.mana.spent_culture as f32)),
+        field("mana", "spent_coring", "Spent on coring", MANA, |m| Some(m.stats.mana.spent_coring as f32)),
+        field("mana", "spent_inflation", "Spent on inflation", MANA, |m| Some(m.stats.mana.spent_inflation as f32)).less_is_better(),
+        field("mana", "spent_ideas", "Spent on ideas", MANA, |m| Some(m.stats.mana.spent_ideas as f32)),
+        field("mana", "spent_force_march", "Spent on force march", MANA, |m| Some(m.stats.mana.spent_force_march as f32)),
+        field("mana", "spent_generals", "Spent on generals", MANA, |m| Some(m.stats.mana.spent_generals as f32)),
+        field("mana", "spent_unjustified", "Spent on unjustified demands", MANA, |m| Some(m.stats.mana.spent_unjustified as f32)).less_is_better(),
+        field("mana", "efficiency", "Share spent on tech, ideas and development", PERCENT, |m| {
+            let productive = m.stats.mana.spent_tech + m.stats.mana.spent_ideas + m.stats.mana.spent_developing.iter().sum::<i32>();
+            Some(productive as f32 / m.stats.mana.mana_spent.iter().sum::<i32>() as f32 * 100.0)
+        }).derived(),
+
+        field("realm", "religious_unity", "Religious unity", PERCENT, |m| m.stats.realm.religious_unity),
+        field("realm", "crown_land", "Crown land", PERCENT, |m| Some(m.stats.realm.crown_land)),
+        field("realm", "government_rank", "Government rank", LEVEL, |m| Some(m.stats.realm.government_rank as f32)),
+
+        field("bloc", "number_countries", "Countries in bloc", COUNT, |m| Some(m.stats.bloc.number_countries as f32)),
+        field("bloc", "total_development", "Bloc development", DEVELOPMENT, |m| Some(m.stats.bloc.total_development)),
+        field("bloc", "income", "Bloc income", DUCATS, |m| Some(m.stats.bloc.income)),
+        field("bloc", "regiments", "Bloc regiments", COUNT, |m| Some(m.stats.bloc.regiments as f32)),
+
+        field("trade", "trade_power", "Trade power", SCORE, |m| Some(m.stats.trade.trade_power)),
+        field("trade", "trade_income", "Trade income", DUCATS, |m| Some(m.stats.trade.trade_income)),
+        field("trade", "merchants", "Merchants", COUNT, |m| Some((m.stats.trade.merchants_steering + m.stats.trade.merchants_collecting) as f32)).derived(),
+    ];
+    return metrics.into_iter().map(|m| Box::new(m) as Box<dyn Metric>).collect();
+}
+
+// Sections of `CountryStats` searched, in order, for a field named without its section
+const SECTIONS: [&str; 8] = ["country", "military", "casualties", "mana", "realm", "bloc", "trade", "anbennac"];
+
+// A path such as `mana.spent_generals` or `powers_earned[2]` into serialized stats
+fn lookup<'a>(value: &'a Value, path: &str) -> Option<&'a Value> {
+    let mut current = value;
+    for part in path.split('.') {
+        let (name, indices) = match part.find('[') {
+            Some(i) => (&part[..i], &part[i..]),
+            None => (part, ""),
+        };
+        current = current.get(name)?;
+        for index in indices.split(']').filter(|i| !i.is_empty()) {
+            current = current.get(index.strip_prefix('[')?.parse::<usize>().ok()?)?;
+        }
+    }
+    return Some(current);
+}
+
+// A field of an expression is, in order:
+// - a metric registered before it, by key (`country.treasury`, `custom.income_per_province`)
+// - a path from the top of the country's stats (`mana.spent_generals`)
+// - a path within one of its sections (`income`, `powers_earned[2]`)
+// - a metric registered before it, by the name after its category (`treasury`)
+fn resolve_field(stats: &Value, computed: &BTreeMap<String, f32>, field: &str) -> Option<f32> {
+    if let Some(value) = computed.get(field) {
+        return Some(*value);
+    }
+    if let Some(value) = lookup_field(stats, field) {
+        return value.as_f64().map(|v| v as f32);
+    }
+    let suffix = format!(".{}", field);
+    return computed.iter().find(|(key, _value)| key.ends_with(&suffix)).map(|(_key, value)| *value);
+}
+
+fn lookup_field<'a>(stats: &'a Value, field: &str) -> Option<&'a Value> {
+    return lookup(stats, field)
+        .or_else(|| SECTIONS.iter().find_map(|section| stats.get(section).and_then(|s| lookup(s, field))));
+}
+
+// Whether `field` resolves for some country, as far as stats with every section tell. Optional
+// values that are missing here, such as religious unity, still count.
+fn is_known_field(stats: &Value, keys: &[&str], field: &str) -> bool {
+    let suffix = format!(".{}", field);
+    return keys.contains(&field) || lookup_field(stats, field).is_some() || keys.iter().any(|k| k.ends_with(&suffix));
+}
+
+pub const CUSTOM: &str = "custom";
+
+pub struct ExpressionMetric {
+    key: String,
+    label: String,
+    unit: String,
+    lower_is_better: bool,
+    expression: Expression,
+}
+
+impl Metric for ExpressionMetric {
+    fn key(&self) -> &str {
+        return &self.key;
+    }
+
+    fn category(&self) -> &str {
+        return CUSTOM;
+    }
+
+    fn label(&self) -> &str {
+        return &self.label;
+    }
+
+    fn unit(&self) -> &str {
+        return &self.unit;
+    }
+
+    fn lower_is_better(&self) -> bool {
+        return self.lower_is_better;
+    }
+
+    fn fields(&self) -> Vec<&str> {
+        return self.expression.fields();
+    }
+
+    fn compute(&self, input: &MetricInput, computed: &BTreeMap<String, f32>) -> Option<f32> {
+        let stats = input.serialized()?;
+        return self.expression.evaluate(&|field: &str| resolve_field(stats, computed, field));
+    }
+}
+
+// One entry of a metrics config file
+#[derive(Debug, Deserialize)]
+struct MetricConfig {
+    name: String,
+    expression: String,
+    label: Option<String>,
+    unit: Option<String>,
+    #[serde(default)]
+    lower_is_better: bool,
+}
+
+// Reads user-defined metrics from a JSON file such as
+//
+//     [
+//         {"name": "income_per_province", "expression": "income / number_provinces", "unit": "ducats"},
+//         {"name": "mil_left", "label": "Military power not spent on generals", "expression": "powers_earned[2] - mana.spent_generals"},
+//         {"name": "losses_per_battle", "expression": "total_land_losses / (battles_won + battles_lost)", "lower_is_better": true}
+//     ]
+//
+// Each becomes the metric `custom.<name>` and may use the custom metrics listed before it.
+pub fn load_metrics<P>(path: P) -> Result<Vec<Box<dyn Metric>>, Box<dyn Error>>
+where P: AsRef<Path> {
+    let configs: Vec<MetricConfig> = serde_json::from_reader(BufReader::new(File::open(&path)?))?;
+    let mut metrics: Vec<Box<dyn Metric>> = Vec::new();
+    for config in configs {
+        metrics.push(Box::new(ExpressionMetric {
+            key: format!("{}.{}", CUSTOM, config.name),
+            label: config.label.unwrap_or_else(|| config.name.clone()),
+            unit: config.unit.unwrap_or_default(),
+            lower_is_better: config.lower_is_better,
+            expression: Expression::parse(&config.expression)?,
+        }));
+    }
+    info!("Loaded {} metrics from {:?}", metrics.len(), path.as_ref());
+    return Ok(metrics);
+}
+
+pub struct MetricRegistry {
+    metrics: Vec<Box<dyn Metric>>,
+}
+
+impl Default for MetricRegistry {
+    fn default() -> Self {
+        MetricRegistry::new()
+    }
+}
+
+impl MetricRegistry {
+    pub fn new() -> MetricRegistry {
+        return MetricRegistry {
+            metrics: default_metrics(),
+        };
+    }
+
+    // The built-in metrics plus those of every `--metrics <config.json>`, removing the flags from
+    // `args` like `Selection::from_args`
+    pub fn from_args(args: &mut Vec<String>) -> Result<MetricRegistry, Box<dyn Error>> {
+        let mut registry = MetricRegistry::new();
+        let mut i = 0;
+        while i < args.len() {
+            if args[i] != "--metrics" {
+                i += 1;
+                continue;
+            }
+            if i + 1 >= args.len() {
+                return Err(format!("Missing value for {}", args[i]).into());
+            }
+            for metric in load_metrics(args.remove(i + 1))? {
+                registry.register(metric);
+            }
+            args.remove(i);
+        }
+        return Ok(registry);
+    }
+
+    // A metric with the key of an existing one replaces it. A misspelled field would leave the
+    // metric out for every country, so unknown fields are warned about here.
+    pub fn register(&mut self, metric: Box<dyn Metric>) {
+        self.metrics.retain(|m| m.key() != metric.key());
+        for field in self.unknown_fields(metric.as_ref()) {
+            warn!("{} reads the unknown field {:?}, it will be left out wherever that field is missing", metric.key(), field);
+        }
+        self.metrics.push(metric);
+    }
+
+    // The fields of `metric` that are neither a metric registered so far nor in the stats
+    fn unknown_fields<'a>(&self, metric: &'a dyn Metric) -> Vec<&'a str> {
+        let stats = CountryStats { anbennac: Some(Default::default()), ..Default::default() };
+        let Ok(stats) = serde_json::to_value(&stats) else { return Vec::new() };
+        let keys: Vec<&str> = self.metrics.iter().map(|m| m.key()).collect();
+        return metric.fields().into_iter().filter(|f| !is_known_field(&stats, &keys, f)).collect();
+    }
+
+    pub fn definitions(&self) -> Vec<MetricDefinition> {
+        return self.metrics.iter().map(|m| MetricDefinition {
+            key: m.key().to_string(),
+            category: m.category().to_string(),
+            label: m.label().to_string(),
+            unit: m.unit().to_string(),
+            path: m.path().map(|p| p.to_string()),
+            lower_is_better: m.lower_is_better(),
+        }).collect();
+    }
+
+    // Every metric by key. Non-finite values, e.g. averages over zero provinces, are left out.
+    pub fn compute(&self, input: &MetricInput) -> BTreeMap<String, f32> {
+        let mut computed = BTreeMap::new();
+        for metric in &self.metrics {
+            if let Some(value) = metric.compute(input, &computed).filter(|v| v.is_finite()) {
+                computed.insert(metric.key().to_string(), value);
+            }
+        }
+        return computed;
+    }
+
+    // The values of `computed` that aren't stored in the stats, for the `metrics` map of the output
+    pub fn unstored(&self, computed: &BTreeMap<String, f32>) -> BTreeMap<String, f32> {
+        return self.metrics.iter()
+            .filter(|m| m.path().is_none())
+            .filter_map(|m| computed.get(m.key()).map(|value| (m.key().to_string(), *value)))
+            .collect();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn stats() -> Value {
+        return json!({
+            "tag": "A01",
+            "country": {"income": 12.5, "powers_earned": [100, 200, 300], "ideas": [["trade_ideas", 7]]},
+            "mana": {"spent_generals": 40, "mana_spent": [50, 60, 70]},
+            "trade": {"income": 3.0},
+        });
+    }
+
+    #[test]
+    fn lookups() {
+        let stats = stats();
+        let cases = [
+            ("country.income", Some(json!(12.5))),
+            ("country.powers_earned[2]", Some(json!(300))),
+            ("country.ideas[0][1]", Some(json!(7))),
+            ("mana.spent_generals", Some(json!(40))),
+            ("tag", Some(json!("A01"))),
+            ("country.powers_earned[3]", None),
+            ("country.powers_earned[x]", None),
+            ("country.missing", None),
+            ("income", None),
+        ];
+        for (path, expected) in cases {
+            assert_eq!(lookup(&stats, path).cloned(), expected, "{}", path);
+        }
+    }
+
+    #[test]
+    fn fields() {
+        let stats = stats();
+        let computed = BTreeMap::from([("country.treasury".to_string(), 250.0)]);
+        let cases = [
+            ("country.treasury", Some(250.0)),
+            ("treasury", Some(250.0)),
+            ("mana.spent_generals", Some(40.0)),
+            ("income", Some(12.5)),
+            ("powers_earned[1]", Some(200.0)),
+            ("mana_spent[0]", Some(50.0)),
+            // Not a number
+            ("tag", None),
+            ("country.ideas", None),
+            ("spent_on_nothing", None),
+        ];
+        for (field, expected) in cases {
+            assert_eq!(resolve_field(&stats, &computed, field), expected, "{}", field);
+        }
+    }
+
+    // Metrics by key, paths from the top, paths within a section in the order of `SECTIONS`, and
+    // metrics by name last
+    #[test]
+    fn field_order() {
+        let stats = json!({
+            "country": {"income": 12.5, "treasury": 1.0, "mana": {"spent_generals": 5}},
+            "mana": {"spent_generals": 40},
+            "trade": {"income": 3.0, "trade_power": 8.0},
+        });
+        let computed = BTreeMap::from([
+            ("country.income".to_string(), 99.0),
+            ("country.treasury".to_string(), 250.0),
+            ("custom.trade_power".to_string(), 7.0),
+            ("custom.score".to_string(), 2.0),
+        ]);
+        let cases = [
+            ("country.income", Some(99.0)),
+            ("mana.spent_generals", Some(40.0)),
+            ("income", Some(12.5)),
+            ("treasury", Some(1.0)),
+            ("trade_power", Some(8.0)),
+            ("score", Some(2.0)),
+        ];
+        for (field, expected) in cases {
+            assert_eq!(resolve_field(&stats, &computed, field), expected, "{}", field);
+        }
+    }
+
+    #[test]
+    fn unknown_fields() {
+        let mut registry = MetricRegistry::new();
+        registry.register(Box::new(ExpressionMetric {
+            key: "custom.per_province".to_string(),
+            label: String::new(),
+            unit: String::new(),
+            lower_is_better: false,
+            expression: Expression::parse("income / number_provinces").unwrap(),
+        }));
+        let cases = [
+            ("income / number_provinces", vec![]),
+            ("mana.spent_generals + powers_earned[2] + country.treasury + treasury", vec![]),
+            // Optional, but a field
+            ("religious_unity + tolerance_own", vec![]),
+            ("custom.per_province * 2 + per_province", vec![]),
+            ("incme / number_provinces", vec!["incme"]),
+            ("custom.per_provice + mana.spent_generls + powers_earned[3]", vec!["custom.per_provice", "mana.spent_generls", "powers_earned[3]"]),
+        ];
+        for (expression, expected) in cases {
+            let metric = ExpressionMetric {
+                key: "custom.test".to_string(),
+                label: String::new(),
+                unit: String::new(),
+                lower_is_better: false,
+                expression: Expression::parse(expression).unwrap(),
+            };
+            assert_eq!(registry.unknown_fields(&metric), expected, "{}", expression);
+        }
+    }
+
+    #[test]
+    fn unstored() {
+        let registry = MetricRegistry::new();
+        let computed = BTreeMap::from([
+            ("country.income".to_string(), 12.5),
+            ("country.treasury".to_string(), 250.0),
+            ("country.adm_tech".to_string(), 5.0),
+            ("mana.spent_developing".to_string(), 80.0),
+        ]);
+        let unstored: Vec<(String, f32)> = registry.unstored(&computed).into_iter().collect();
+        assert_eq!(unstored, [("country.treasury".to_string(), 250.0), ("mana.spent_developing".to_string(), 80.0)]);
+
+        // Every built-in metric is either a field at its path or in the `metrics` map
+        let definitions = registry.definitions();
+        let stored: Vec<&str> = definitions.iter().filter_map(|d| d.path.as_deref()).collect();
+        assert!(stored.contains(&"country.income"));
+        assert!(stored.contains(&"country.technology[0]"));
+        assert!(!stored.iter().any(|p| p.starts_with("custom.")));
+    }
+}
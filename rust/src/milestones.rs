@@ -0,0 +1,233 @@
+// Milestones reached between consecutive saves of a campaign, for a "campaign news" feed. Each is
+// a threshold of a metric (1000 development, admin tech 20, ...) or becoming a great power. What
+// was already reached in the first save only counts towards who was first.
+use log::info;
+use std::collections::HashSet;
+use std::error::Error;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+use std::result::Result;
+
+use crate::models::{CountryStats, Eu4Stats, MilestoneEvent};
+
+struct Milestone {
+    kind: &'static str,
+    metric: &'static str,
+    thresholds: &'static [f32],
+    describe: fn(f32) -> String,
+}
+
+fn get_milestones() -> Vec<Milestone> {
+    return vec![
+        Milestone { kind: "development", metric: "country.total_development", thresholds: &[500.0, 1000.0, 2000.0, 5000.0], describe: |t| format!("{} development", t) },
+        Milestone { kind: "adm_tech", metric: "country.adm_tech", thresholds: &[10.0, 20.0, 30.0], describe: |t| format!("admin tech {}", t) },
+        Milestone { kind: "dip_tech", metric: "country.dip_tech", thresholds: &[10.0, 20.0, 30.0], describe: |t| format!("diplomatic tech {}", t) },
+        Milestone { kind: "mil_tech", metric: "country.mil_tech", thresholds: &[10.0, 20.0, 30.0], describe: |t| format!("military tech {}", t) },
+        Milestone { kind: "income", metric: "country.income", thresholds: &[50.0, 100.0, 200.0, 500.0], describe: |t| format!("{} ducats of monthly income", t) },
+        Milestone { kind: "full_idea_groups", metric: "country.full_idea_groups", thresholds: &[1.0, 4.0, 8.0], describe: |t| if t == 1.0 { "a full idea group".to_string() } else { format!("{} full idea groups", t) } },
+        Milestone { kind: "army_professionalism", metric: "military.army_professionalism", thresholds: &[100.0], describe: |t| format!("{}% army professionalism", t) },
+    ];
+}
+
+fn milestone_key(kind: &str, threshold: f32) -> String {
+    return format!("{}:{}", kind, threshold);
+}
+
+// The milestones `country` has reached
+fn get_reached(country: &CountryStats, milestones: &[Milestone]) -> Vec<String> {
+    let mut reached = Vec::new();
+    for milestone in milestones {
+        let Some(value) = country.metric_values.get(milestone.metric) else { continue };
+        reached.extend(milestone.thresholds.iter().filter(|t| value >= *t).map(|t| milestone_key(milestone.kind, *t)));
+    }
+    return reached;
+}
+
+fn new_event(stats: &Eu4Stats, previous: &Eu4Stats, country: &CountryStats, kind: &str) -> MilestoneEvent {
+    return MilestoneEvent {
+        date: stats.meta.date.clone(),
+        previous_date: previous.meta.date.clone(),
+        tag: country.tag.clone(),
+        name: country.name.clone(),
+        player: country.player.clone(),
+        kind: kind.to_string(),
+        ..Default::default()
+    };
+}
+
+// The same nation (see `Eu4Stats::find_previous`), played by the same player or by the AI in both
+// saves. A player taking over a nation isn't news for that nation.
+fn find_previous<'a>(previous: &'a Eu4Stats, country: &CountryStats) -> Option<&'a CountryStats> {
+    return previous.find_previous(country).filter(|c| c.player == country.player);
+}
+
+// Countries without a match in the previous save are skipped
+fn detect_between(stats: &Eu4Stats, previous: &Eu4Stats, milestones: &[Milestone], reached: &HashSet<String>) -> Vec<MilestoneEvent> {
+    let mut events = Vec::new();
+    for country in &stats.countries {
+        let Some(old_country) = find_previous(previous, country) else { continue };
+        if country.country.great_power && !old_country.country.great_power {
+            events.push(MilestoneEvent {
+                description: format!("{} became a great power", country.name),
+                ..new_event(stats, previous, country, "great_power")
+            });
+        }
+        for milestone in milestones {
+            let Some(value) = country.metric_values.get(milestone.metric) else { continue };
+            let old_value = old_country.metric_values.get(milestone.metric).copied().unwrap_or(f32::NEG_INFINITY);
+            for threshold in milestone.thresholds.iter().filter(|t| old_value < **t && *value >= **t) {
+                let first = !reached.contains(&milestone_key(milestone.kind, *threshold));
+                let what = (milestone.describe)(*threshold);
+                events.push(MilestoneEvent {
+                    threshold: Some(*threshold),
+                    value: Some(*value),
+                    first,
+                    description: if first {
+                        format!("{} is the first to reach {}", country.name, what)
+                    } else {
+                        format!("{} reached {}", country.name, what)
+                    },
+                    ..new_event(stats, previous, country, milestone.kind)
+                });
+            }
+        }
+    }
+    // Firsts lead the news of each session
+    events.sort_by_key(|e| !e.first);
+    return events;
+}
+
+// `saves` may come in any order, they are sorted by in-game date
+pub fn detect_milestones(saves: &[Eu4Stats]) -> Vec<MilestoneEvent> {
+    let mut saves: Vec<&Eu4Stats> = saves.iter().collect();
+    saves.sort_by(|a, b| a.meta.date.cmp(&b.meta.date));
+    let milestones = get_milestones();
+
+    let mut reached: HashSet<String> = HashSet::new();
+    let mut events = Vec::new();
+    for (i, stats) in saves.iter().enumerate() {
+        if i > 0 {
+            events.extend(detect_between(stats, saves[i - 1], &milestones, &reached));
+        }
+        reached.extend(stats.countries.iter().flat_map(|c| get_reached(c, &milestones)));
+    }
+    info!("Detected {} milestones in {} saves", events.len(), saves.len());
+    return events;
+}
+
+pub fn write_milestones<P>(events: &[MilestoneEvent], path: P) -> Result<(), Box<dyn Error>>
+where P: AsRef<Path> {
+    let file = File::create(&path)?;
+    let mut writer = BufWriter::new(file);
+    serde_json::to_writer_pretty(&mut writer, events)?;
+    writeln!(writer)?;
+    writer.flush()?;
+    info!("Finished writing milestones to {:?}", path.as_ref());
+    return Ok(());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{CondensedCountry, SaveMeta};
+
+    fn country(tag: &str, player: Option<&str>, great_power: bool, development: f32) -> CountryStats {
+        return CountryStats {
+            tag: tag.to_string(),
+            name: tag.to_string(),
+            player: player.map(|p| p.to_string()),
+            country: CondensedCountry { great_power, ..Default::default() },
+            metric_values: [("country.total_development".to_string(), development)].into_iter().collect(),
+            ..Default::default()
+        };
+    }
+
+    fn save(date: &str, countries: Vec<CountryStats>) -> Eu4Stats {
+        return Eu4Stats {
+            meta: SaveMeta { date: date.to_string(), ..Default::default() },
+            countries,
+            ..Default::default()
+        };
+    }
+
+    fn get_events(saves: &[Eu4Stats]) -> Vec<(String, String, Option<f32>, bool)> {
+        return detect_milestones(saves).into_iter().map(|e| (e.tag, e.kind, e.threshold, e.first)).collect();
+    }
+
+    fn event(tag: &str, kind: &str, threshold: Option<f32>, first: bool) -> (String, String, Option<f32>, bool) {
+        return (tag.to_string(), kind.to_string(), threshold, first);
+    }
+
+    #[test]
+    fn milestones() {
+        let cases = [
+            // Nothing changed
+            (
+                vec![country("A01", Some("Alice"), false, 400.0)],
+                vec![country("A01", Some("Alice"), false, 450.0)],
+                vec![],
+            ),
+            // Thresholds crossed, only the first to reach one is first
+            (
+                vec![country("A01", Some("Alice"), false, 400.0), country("B01", None, false, 600.0)],
+                vec![country("A01", Some("Alice"), false, 600.0), country("B01", None, false, 1100.0)],
+                vec![event("B01", "development", Some(1000.0), true), event("A01", "development", Some(500.0), false)],
+            ),
+            // Several thresholds at once
+            (
+                vec![country("A01", None, false, 400.0)],
+                vec![country("A01", None, false, 2500.0)],
+                vec![
+                    event("A01", "development", Some(500.0), true),
+                    event("A01", "development", Some(1000.0), true),
+                    event("A01", "development", Some(2000.0), true),
+                ],
+            ),
+            // Becoming a great power, staying one isn't news
+            (
+                vec![country("A01", None, false, 100.0), country("B01", None, true, 100.0)],
+                vec![country("A01", None, true, 100.0), country("B01", None, true, 100.0)],
+                vec![event("A01", "great_power", None, false)],
+            ),
+            // A player switching to a larger great power isn't compared with the nation they left,
+            // and neither is the AI that took it over
+            (
+                vec![country("A01", Some("Alice"), false, 400.0), country("B01", None, true, 1200.0)],
+                vec![country("A01", None, false, 400.0), country("B01", Some("Alice"), true, 1200.0)],
+                vec![],
+            ),
+            // A formed nation is compared with its former tag
+            (
+                vec![country("A01", Some("Alice"), false, 400.0)],
+                vec![CountryStats { previous_tags: vec!["A01".to_string()], ..country("A02", Some("Alice"), true, 600.0) }],
+                vec![event("A02", "development", Some(500.0), true), event("A02", "great_power", None, false)],
+            ),
+            // Countries that didn't exist before are skipped
+            (
+                vec![],
+                vec![country("A01", None, true, 600.0)],
+                vec![],
+            ),
+        ];
+        // Firsts come first
+        for (i, (old, new, expected)) in cases.into_iter().enumerate() {
+            // Given newest first, the saves are still compared by date
+            let events = get_events(&[save("1460.1.1", new), save("1450.1.1", old)]);
+            assert_eq!(events, expected, "case {}", i);
+        }
+    }
+
+    // What was reached in any earlier save counts towards who was first
+    #[test]
+    fn first_across_saves() {
+        let saves = [
+            save("1450.1.1", vec![country("A01", None, false, 400.0), country("B01", None, false, 400.0)]),
+            save("1460.1.1", vec![country("A01", None, false, 600.0), country("B01", None, false, 400.0)]),
+            save("1470.1.1", vec![country("A01", None, false, 600.0), country("B01", None, false, 700.0)]),
+        ];
+        let events = detect_milestones(&saves);
+        let found: Vec<_> = events.iter().map(|e| (e.tag.as_str(), e.date.as_str(), e.previous_date.as_str(), e.first)).collect();
+        assert_eq!(found, [("A01", "1460.1.1", "1450.1.1", true), ("B01", "1470.1.1", "1460.1.1", false)]);
+    }
+}
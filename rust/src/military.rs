@@ -0,0 +1,559 @@
+use eu4save::CountryTag;
+use eu4save::models::{Country, GameState};
+
+// What the military stat calculators read from a country. It is implemented for eu4save's
+// `Country`, and for a small builder in the tests so every formula can be checked without a save.
+pub trait StatInput {
+    fn idea_groups(&self) -> Vec<(&str, u8)>;
+    fn policies(&self) -> Vec<&str>;
+    // Personalities of the current ruler
+    fn ruler_personalities(&self) -> Vec<&str>;
+    fn absolutism(&self) -> f32;
+    fn army_tradition(&self) -> f32;
+    fn army_professionalism(&self) -> f32;
+    fn power_projection(&self) -> f32;
+    fn regiment_morale(&self) -> Vec<f32>;
+    fn ship_morale(&self) -> Vec<f32>;
+}
+
+impl StatInput for Country {
+    fn idea_groups(&self) -> Vec<(&str, u8)> {
+        return self.active_idea_groups.iter().map(|(name, amt)| (name.as_str(), *amt)).collect();
+    }
+
+    fn policies(&self) -> Vec<&str> {
+        return self.active_policies.iter().map(|p| p.policy.as_str()).collect();
+    }
+
+    fn ruler_personalities(&self) -> Vec<&str> {
+        let events = &self.history.events;
+        let Some((_date, last_monarch)) = events.iter().rev().find(|(_k, v)| v.as_monarch().is_some()) else {
+            return Vec::new();
+        };
+        return last_monarch.as_monarch().unwrap().personalities.iter().map(|(p, _)| p.as_str()).collect();
+    }
+
+    fn absolutism(&self) -> f32 {
+        return self.absolutism;
+    }
+
+    fn army_tradition(&self) -> f32 {
+        return self.army_tradition;
+    }
+
+    fn army_professionalism(&self) -> f32 {
+        return self.army_professionalism;
+    }
+
+    fn power_projection(&self) -> f32 {
+        return self.current_power_projection;
+    }
+
+    fn regiment_morale(&self) -> Vec<f32> {
+        return self.armies.iter().flat_map(|a| a.regiments.iter().map(|r| r.morale)).collect();
+    }
+
+    fn ship_morale(&self) -> Vec<f32> {
+        return self.navies.iter().flat_map(|n| n.ships.iter().map(|s| s.morale)).collect();
+    }
+}
+
+// Progress of the hegemonies a country holds, which live in the gamestate rather than the country
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Hegemonies {
+    pub military: Option<f32>,
+    pub economic: Option<f32>,
+}
+
+impl Hegemonies {
+    pub fn of(tag: &CountryTag, gamestate: &GameState) -> Hegemonies {
+        return Hegemonies {
+            military: gamestate.military_hegemon.as_ref().filter(|h| h.country == *tag).map(|h| h.progress),
+            economic: gamestate.economic_hegemon.as_ref().filter(|h| h.country == *tag).map(|h| h.progress),
+        };
+    }
+}
+
+pub fn get_discipline(country: &impl StatInput) -> f32 {
+    let mut base: f32 = 100.0;
+    // Ideas
+    for (name, amt) in country.idea_groups() {
+        if (name.contains("offensive") || name.contains("quality")) && amt >= 7 {
+            base += 5.0;
+        }
+    }
+
+    // Policies
+    for policy in country.policies() {
+        if policy.contains("weapon_quality") {
+            base += 5.0;
+        } else if policy.contains("on_our_terms") {
+            base += 2.5;
+        }
+    }
+
+    // Advisor
+    // TBD
+
+    // Monarch
+    for personality in country.ruler_personalities() {
+        if personality.contains("strict") {
+            base += 5.0;
+        }
+    }
+
+    // Absolutism
+    base += f32::min(country.absolutism(), 100.0) / 20.0;
+
+    return base;
+}
+
+pub fn get_army_morale(country: &impl StatInput) -> f32 {
+    // If you're drilling, then lol
+    let mut morale: f32 = 0.0;
+    for r in country.regiment_morale() {
+        morale = morale.max(r);
+    }
+    return morale;
+}
+
+pub fn get_force_limit(country: &impl StatInput) -> i32 {
+    // This gets the total number of troops currently, not FL
+    return country.regiment_morale().len() as i32;
+}
+
+pub fn get_siege_ability(country: &impl StatInput, hegemonies: &Hegemonies) -> f32 {
+    let mut base: f32 = 0.0;
+    // Ideas
+    for (name, amt) in country.idea_groups() {
+        if name.contains("offensive") && amt >= 5 {
+            base += 20.0;
+        } else if name.contains("espionage") && amt >= 3 {
+            base += 10.0;
+        }
+    }
+
+    // Policies
+    for p in country.policies() {
+        if p.contains("word_is_my_bond") || p.contains("fear_tactics") || p.contains("siege_weapons") || p.contains("military_zeal"){
+            base += 10.0;
+        }
+    }
+
+    // War Exhaustion
+    // TBD
+
+    // Army Tradition
+    base += country.army_tradition() / 20.0;
+
+    // Army Professionalism
+    base += country.army_professionalism() / 0.05;
+
+    // Military Hegemon
+    if hegemonies.military.is_some_and(|progress| progress >= 100.0) {
+        base += 20.0;
+    }
+
+    return base;
+}
+
+pub fn get_fort_defense(country: &impl StatInput) -> f32 {
+    let mut base: f32 = 0.0;
+    // Ideas
+    for (name, amt) in country.idea_groups() {
+        if name.contains("defensive") && amt >= 5 {
+            base += 25.0;
+        }
+    }
+
+    // Policies
+    for p in country.policies() {
+        if p.contains("for the people") {
+            base += 25.0;
+        } else if p.contains("privy_council") || p.contains("loyal_conduct") {
+            base += 15.0;
+        } else if p.contains("superior_fortifications") {
+            base += 10.0;
+        }
+    }
+
+    // Power Projection
+    base += country.power_projection() / 10.0;
+
+    return base;
+}
+
+pub fn get_infantry_ca(country: &impl StatInput) -> f32 {
+    let mut base: f32 = 0.0;
+    // Ideas
+    for (name, amt) in country.idea_groups() {
+        if name.contains("mercenary") && amt >= 6 {
+            base += 10.0;
+        } else if name.contains("quality") && amt >= 1 {
+            base += 10.0;
+        }
+    }
+
+    // Policies
+    for p in country.policies() {
+        if p.contains("modern_firearm") {
+            base += 15.0;
+        }
+    }
+
+    return base;
+}
+
+pub fn get_cavalry_ca(country: &impl StatInput) -> f32 {
+    let mut base: f32 = 0.0;
+    // Ideas
+    for (name, amt) in country.idea_groups() {
+        if name.contains("horde") && amt >= 7 {
+            base += 25.0;
+        } else if name.contains("aristocratic") && amt >= 1 {
+            base += 15.0;
+        } else if name.contains("quality") && amt >= 3 {
+            base += 10.0;
+        }
+    }
+
+    // Policies
+    for p in country.policies() {
+        if p.contains("noble_loyalty") || p.contains("psychological") {
+            base += 10.0;
+        }
+    }
+
+    return base;
+}
+
+pub fn get_artillery_ca(country: &impl StatInput) -> f32 {
+    let mut base: f32 = 0.0;
+    // Ideas
+    for (name, amt) in country.idea_groups() {
+        if name.contains("quality") && amt >= 7 {
+            base += 10.0;
+        }
+    }
+
+    // Policies
+    for p in country.policies() {
+        if p.contains("horse_artillery") {
+            base += 10.0;
+        }
+    }
+
+    return base;
+}
+
+// Leader pips come from one idea group at a given level and one policy
+fn get_leader_pips(country: &impl StatInput, idea_group: &str, level: u8, policy: &str) -> u8 {
+    let mut base: u8 = 0;
+    // Ideas
+    for (name, amt) in country.idea_groups() {
+        if name.contains(idea_group) && amt >= level {
+            base += 1;
+        }
+    }
+
+    // Policies
+    for p in country.policies() {
+        if p.contains(policy) {
+            base += 1;
+        }
+    }
+
+    return base;
+}
+
+pub fn get_leader_fire(country: &impl StatInput) -> u8 {
+    return get_leader_pips(country, "offensive", 3, "mining_act");
+}
+
+pub fn get_leader_shock(country: &impl StatInput) -> u8 {
+    return get_leader_pips(country, "offensive", 1, "inspirational_leaders");
+}
+
+pub fn get_leader_maneuver(country: &impl StatInput) -> u8 {
+    return get_leader_pips(country, "defensive", 3, "hired_adventurers");
+}
+
+pub fn get_leader_siege(country: &impl StatInput) -> u8 {
+    return get_leader_pips(country, "aristocratic", 7, "modern_siege");
+}
+
+pub fn get_navy_morale(country: &impl StatInput) -> f32 {
+    // If you're drilling, then lol
+    let mut morale: f32 = 0.0;
+    for s in country.ship_morale() {
+        morale = morale.max(s);
+    }
+    return morale;
+}
+
+pub fn get_navy_force_limit(country: &impl StatInput) -> i32 {
+    // This gets the total number of ships currently, not FL
+    return country.ship_morale().len() as i32;
+}
+
+pub fn get_merc_discipline(country: &impl StatInput, hegemonies: &Hegemonies) -> f32 {
+    let mut base: f32 = 100.0;
+    // Ideas
+    for (name, amt) in country.idea_groups() {
+        if name.contains("mercenary") && amt >= 7 {
+            base += 5.0;
+        }
+    }
+
+    // Policies
+    for p in country.policies() {
+        if p.contains("mercenary_tactical") {
+            base += 5.0;
+        }
+    }
+
+    // Economic Hegemon
+    if let Some(progress) = hegemonies.economic {
+        base += progress / 10.0;
+    }
+
+    return base;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, Default)]
+    struct TestCountry {
+        idea_groups: Vec<(String, u8)>,
+        policies: Vec<String>,
+        personalities: Vec<String>,
+        absolutism: f32,
+        army_tradition: f32,
+        army_professionalism: f32,
+        power_projection: f32,
+        regiments: Vec<f32>,
+        ships: Vec<f32>,
+    }
+
+    impl TestCountry {
+        fn idea(mut self, name: &str, amt: u8) -> Self {
+            self.idea_groups.push((name.to_string(), amt));
+            return self;
+        }
+
+        fn policy(mut self, name: &str) -> Self {
+            self.policies.push(name.to_string());
+            return self;
+        }
+
+        fn personality(mut self, name: &str) -> Self {
+            self.personalities.push(name.to_string());
+            return self;
+        }
+
+        fn absolutism(mut self, absolutism: f32) -> Self {
+            self.absolutism = absolutism;
+            return self;
+        }
+
+        fn army_tradition(mut self, army_tradition: f32) -> Self {
+            self.army_tradition = army_tradition;
+            return self;
+        }
+
+        fn army_professionalism(mut self, army_professionalism: f32) -> Self {
+            self.army_professionalism = army_professionalism;
+            return self;
+        }
+
+        fn power_projection(mut self, power_projection: f32) -> Self {
+            self.power_projection = power_projection;
+            return self;
+        }
+
+        fn regiments(mut self, morale: &[f32]) -> Self {
+            self.regiments = morale.to_vec();
+            return self;
+        }
+
+        fn ships(mut self, morale: &[f32]) -> Self {
+            self.ships = morale.to_vec();
+            return self;
+        }
+    }
+
+    impl StatInput for TestCountry {
+        fn idea_groups(&self) -> Vec<(&str, u8)> {
+            return self.idea_groups.iter().map(|(name, amt)| (name.as_str(), *amt)).collect();
+        }
+
+        fn policies(&self) -> Vec<&str> {
+            return self.policies.iter().map(|p| p.as_str()).collect();
+        }
+
+        fn ruler_personalities(&self) -> Vec<&str> {
+            return self.personalities.iter().map(|p| p.as_str()).collect();
+        }
+
+        fn absolutism(&self) -> f32 {
+            return self.absolutism;
+        }
+
+        fn army_tradition(&self) -> f32 {
+            return self.army_tradition;
+        }
+
+        fn army_professionalism(&self) -> f32 {
+            return self.army_professionalism;
+        }
+
+        fn power_projection(&self) -> f32 {
+            return self.power_projection;
+        }
+
+        fn regiment_morale(&self) -> Vec<f32> {
+            return self.regiments.clone();
+        }
+
+        fn ship_morale(&self) -> Vec<f32> {
+            return self.ships.clone();
+        }
+    }
+
+    fn country() -> TestCountry {
+        return TestCountry::default();
+    }
+
+    const NO_HEGEMONY: Hegemonies = Hegemonies { military: None, economic: None };
+
+    #[test]
+    fn discipline() {
+        let cases = [
+            (country(), 100.0),
+            (country().idea("quality_ideas", 7), 105.0),
+            (country().idea("quality_ideas", 6), 100.0),
+            (country().idea("offensive_ideas", 7).idea("quality_ideas", 7), 110.0),
+            (country().policy("weapon_quality_policy"), 105.0),
+            (country().policy("on_our_terms_policy"), 102.5),
+            (country().personality("strict_personality"), 105.0),
+            (country().absolutism(50.0), 102.5),
+            (country().absolutism(120.0), 105.0),
+        ];
+        for (country, expected) in cases {
+            assert_eq!(get_discipline(&country), expected, "{:?}", country);
+        }
+    }
+
+    #[test]
+    fn army_morale_and_force_limit() {
+        let cases = [
+            (country(), 0.0, 0),
+            (country().regiments(&[2.5, 3.1, 2.0]), 3.1, 3),
+        ];
+        for (country, morale, force_limit) in cases {
+            assert_eq!(get_army_morale(&country), morale, "{:?}", country);
+            assert_eq!(get_force_limit(&country), force_limit, "{:?}", country);
+        }
+    }
+
+    #[test]
+    fn siege_ability() {
+        let hegemon = Hegemonies { military: Some(100.0), economic: None };
+        let rising = Hegemonies { military: Some(60.0), economic: None };
+        let cases = [
+            (country(), NO_HEGEMONY, 0.0),
+            (country().idea("offensive_ideas", 5), NO_HEGEMONY, 20.0),
+            (country().idea("offensive_ideas", 4), NO_HEGEMONY, 0.0),
+            (country().idea("espionage_ideas", 3), NO_HEGEMONY, 10.0),
+            (country().policy("siege_weapons_policy").policy("military_zeal_act"), NO_HEGEMONY, 20.0),
+            (country().army_tradition(40.0), NO_HEGEMONY, 2.0),
+            (country().army_professionalism(0.5), NO_HEGEMONY, 10.0),
+            (country(), hegemon, 20.0),
+            (country(), rising, 0.0),
+        ];
+        for (country, hegemonies, expected) in cases {
+            assert_eq!(get_siege_ability(&country, &hegemonies), expected, "{:?} {:?}", country, hegemonies);
+        }
+    }
+
+    #[test]
+    fn fort_defense() {
+        let cases = [
+            (country(), 0.0),
+            (country().idea("defensive_ideas", 5), 25.0),
+            (country().policy("privy_council_policy"), 15.0),
+            (country().policy("superior_fortifications_policy"), 10.0),
+            (country().power_projection(50.0), 5.0),
+        ];
+        for (country, expected) in cases {
+            assert_eq!(get_fort_defense(&country), expected, "{:?}", country);
+        }
+    }
+
+    #[test]
+    fn combat_abilities() {
+        let cases = [
+            (country(), [0.0, 0.0, 0.0]),
+            (country().idea("quality_ideas", 1), [10.0, 0.0, 0.0]),
+            (country().idea("quality_ideas", 7), [10.0, 10.0, 10.0]),
+            (country().idea("mercenary_ideas", 6), [10.0, 0.0, 0.0]),
+            (country().idea("horde_ideas", 7), [0.0, 25.0, 0.0]),
+            (country().idea("aristocratic_ideas", 1), [0.0, 15.0, 0.0]),
+            (country().policy("modern_firearm_techniques"), [15.0, 0.0, 0.0]),
+            (country().policy("psychological_warfare_act"), [0.0, 10.0, 0.0]),
+            (country().policy("horse_artillery_act"), [0.0, 0.0, 10.0]),
+        ];
+        for (country, [infantry, cavalry, artillery]) in cases {
+            assert_eq!(get_infantry_ca(&country), infantry, "{:?}", country);
+            assert_eq!(get_cavalry_ca(&country), cavalry, "{:?}", country);
+            assert_eq!(get_artillery_ca(&country), artillery, "{:?}", country);
+        }
+    }
+
+    #[test]
+    fn leader_pips() {
+        let cases = [
+            (country(), [0, 0, 0, 0]),
+            (country().idea("offensive_ideas", 1), [0, 1, 0, 0]),
+            (country().idea("offensive_ideas", 3), [1, 1, 0, 0]),
+            (country().idea("defensive_ideas", 3), [0, 0, 1, 0]),
+            (country().idea("aristocratic_ideas", 7), [0, 0, 0, 1]),
+            (country().policy("mining_act").policy("inspirational_leaders_act"), [1, 1, 0, 0]),
+            (country().policy("hired_adventurers_act").policy("modern_siege_weapons"), [0, 0, 1, 1]),
+        ];
+        for (country, [fire, shock, maneuver, siege]) in cases {
+            assert_eq!(get_leader_fire(&country), fire, "{:?}", country);
+            assert_eq!(get_leader_shock(&country), shock, "{:?}", country);
+            assert_eq!(get_leader_maneuver(&country), maneuver, "{:?}", country);
+            assert_eq!(get_leader_siege(&country), siege, "{:?}", country);
+        }
+    }
+
+    #[test]
+    fn navy_morale_and_force_limit() {
+        let cases = [
+            (country(), 0.0, 0),
+            (country().ships(&[1.5, 4.0]), 4.0, 2),
+        ];
+        for (country, morale, force_limit) in cases {
+            assert_eq!(get_navy_morale(&country), morale, "{:?}", country);
+            assert_eq!(get_navy_force_limit(&country), force_limit, "{:?}", country);
+        }
+    }
+
+    #[test]
+    fn mercenary_discipline() {
+        let hegemon = Hegemonies { military: None, economic: Some(80.0) };
+        let cases = [
+            (country(), NO_HEGEMONY, 100.0),
+            (country().idea("mercenary_ideas", 7), NO_HEGEMONY, 105.0),
+            (country().policy("mercenary_tactical_act"), NO_HEGEMONY, 105.0),
+            (country(), hegemon, 108.0),
+        ];
+        for (country, hegemonies, expected) in cases {
+            assert_eq!(get_merc_discipline(&country, &hegemonies), expected, "{:?} {:?}", country, hegemonies);
+        }
+    }
+}
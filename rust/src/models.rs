@@ -1,187 +1,496 @@
-use jomini::JominiDeserialize;
-use serde::ser::{Serialize, SerializeStruct, Serializer};
-
-#[derive(Debug, Clone, JominiDeserialize, Default)]
-#[cfg_attr(feature = "serialize", derive(Serialize))]
-pub struct CondensedCountry {
-    pub total_development: f32,
-    pub real_development: f32,
-    pub gp_score: i32,
-    pub powers_earned: [i32; 3],
-    pub technology: [i32; 3],
-    pub ideas: Vec<(String, u8)>,
-    pub total_ideas: u8,
-    pub current_manpower: i32,
-    pub max_manpower: i32,
-    pub average_monarch: [f32; 3],
-    pub income: f32,
-    pub income_history: Box<[(u16, i32)]>,
-    pub number_provinces: i32,
-    pub number_buildings: i32,
-    pub buildings_value: i32,
-    pub buildings_per_province: f32,
-    pub innovativeness: f32,
-    pub absolutism: f32,
-    pub average_development: f32,
-    pub average_development_real: f32,
-}
-
-impl Serialize for CondensedCountry {
-    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
-    where S: Serializer, {
-        let mut s = serializer.serialize_struct("CondensedCountry", 20)?;
-        s.serialize_field("total_development", &self.total_development)?;
-        s.serialize_field("real_development", &self.real_development)?;
-        s.serialize_field("gp_score", &self.gp_score)?;
-        s.serialize_field("powers_earned", &self.powers_earned)?;
-        s.serialize_field("technology", &self.technology)?;
-        s.serialize_field("ideas", &self.ideas)?;
-        s.serialize_field("total_ideas", &self.total_ideas)?;
-        s.serialize_field("current_manpower", &self.current_manpower)?;
-        s.serialize_field("max_manpower", &self.max_manpower)?;
-        s.serialize_field("average_monarch", &self.average_monarch)?;
-        s.serialize_field("income", &self.income)?;
-        s.serialize_field("income_history", &self.income_history)?;
-        s.serialize_field("number_provinces", &self.number_provinces)?;
-        s.serialize_field("number_buildings", &self.number_buildings)?;
-        s.serialize_field("buildings_value", &self.buildings_value)?;
-        s.serialize_field("buildings_per_province", &self.buildings_per_province)?;
-        s.serialize_field("innovativeness", &self.innovativeness)?;
-        s.serialize_field("absolutism", &self.absolutism)?;
-        s.serialize_field("average_development", &self.average_development)?;
-        s.serialize_field("average_development_real", &self.average_development_real)?;
-        s.end()
-    }
-}
-
-#[derive(Debug, Clone, JominiDeserialize, Default)]
-#[cfg_attr(feature = "serialize", derive(Serialize))]
-pub struct CountryMilitary {
-    pub army_tradition: f32,
-    pub army_morale: f32,
-    pub army_discipline: f32,
-    pub army_force_limit: i32,
-    pub army_professionalism: f32,
-    pub siege_ability: f32,
-    pub fort_defense: f32,
-    pub infantry_ability: f32,
-    pub cavalry_ability: f32,
-    pub artillery_ability: f32,
-    pub fire_dealt: f32,
-    pub fire_received: f32,
-    pub shock_dealt: f32,
-    pub shock_received: f32,
-    pub leader_fire: u8,
-    pub leader_shock: u8,
-    pub leader_maneuver: u8,
-    pub leader_siege: u8,
-    pub mercenary_discipline: f32,
-    pub naval_tradition: f32,
-    pub naval_morale: f32,
-    pub naval_force_limit: i32,
-}
-
-impl Serialize for CountryMilitary {
-    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
-    where S: Serializer, {
-        let mut s = serializer.serialize_struct("CountryMilitary", 22)?;
-        s.serialize_field("army_tradition", &self.army_tradition)?;
-        s.serialize_field("army_morale", &self.army_morale)?;
-        s.serialize_field("army_discipline", &self.army_discipline)?;
-        s.serialize_field("army_force_limit", &self.army_force_limit)?;
-        s.serialize_field("army_professionalism", &self.army_professionalism)?;
-        s.serialize_field("siege_ability", &self.siege_ability)?;
-        s.serialize_field("fort_defense", &self.fort_defense)?;
-        s.serialize_field("infantry_ability", &self.infantry_ability)?;
-        s.serialize_field("cavalry_ability", &self.cavalry_ability)?;
-        s.serialize_field("artillery_ability", &self.artillery_ability)?;
-        s.serialize_field("fire_dealt", &self.fire_dealt)?;
-        s.serialize_field("fire_received", &self.fire_received)?;
-        s.serialize_field("shock_dealt", &self.shock_dealt)?;
-        s.serialize_field("shock_received", &self.shock_received)?;
-        s.serialize_field("leader_fire", &self.leader_fire)?;
-        s.serialize_field("leader_shock", &self.leader_shock)?;
-        s.serialize_field("leader_maneuver", &self.leader_maneuver)?;
-        s.serialize_field("leader_siege", &self.leader_siege)?;
-        s.serialize_field("mercenary_discipline", &self.mercenary_discipline)?;
-        s.serialize_field("naval_tradition", &self.naval_tradition)?;
-        s.serialize_field("naval_morale", &self.naval_morale)?;
-        s.serialize_field("naval_force_limit", &self.naval_force_limit)?;
-        s.end()
-    }
-}
-
-#[derive(Debug, Clone, JominiDeserialize, Default)]
-#[cfg_attr(feature = "serialize", derive(Serialize))]
-pub struct CountryMana {
-    pub mana_spent: [i32; 3],
-    pub spent_developing: [i32; 3],
-    pub developing_ratio: String,
-    pub spent_tech: i32,
-    pub spent_culture: i32,
-    pub spent_coring: i32,
-    pub spent_inflation: i32,
-    pub spent_ideas: i32,
-    pub spent_force_march: i32,
-    pub spent_generals: i32,
-    pub spent_unjustified: i32,
-}
-
-impl Serialize for CountryMana {
-    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
-    where S: Serializer, {
-        let mut s = serializer.serialize_struct("CountryMana", 11)?;
-        s.serialize_field("mana_spent", &self.mana_spent)?;
-        s.serialize_field("spent_developing", &self.spent_developing)?;
-        s.serialize_field("developing_ratio", &self.developing_ratio)?;
-        s.serialize_field("spent_tech", &self.spent_tech)?;
-        s.serialize_field("spent_culture", &self.spent_culture)?;
-        s.serialize_field("spent_coring", &self.spent_coring)?;
-        s.serialize_field("spent_inflation", &self.spent_inflation)?;
-        s.serialize_field("spent_ideas", &self.spent_ideas)?;
-        s.serialize_field("spent_force_march", &self.spent_force_march)?;
-        s.serialize_field("spent_generals", &self.spent_generals)?;
-        s.serialize_field("spent_unjustified", &self.spent_unjustified)?;
-        s.end()
-    }
-}
-
-#[derive(Debug, Clone, JominiDeserialize, Default)]
-#[cfg_attr(feature = "serialize", derive(Serialize))]
-pub struct CountryStats {
-    pub tag: String,
-    pub name: String,
-    pub player: Option<String>,
-    pub country: CondensedCountry,
-    pub military: CountryMilitary,
-    pub mana: CountryMana,
-}
-
-impl Serialize for CountryStats {
-    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
-    where S: Serializer, {
-        let mut s = serializer.serialize_struct("CountryStats", 6)?;
-        s.serialize_field("tag", &self.tag)?;
-        s.serialize_field("name", &self.name)?;
-        s.serialize_field("player", &self.player)?;
-        s.serialize_field("country", &self.country)?;
-        s.serialize_field("military", &self.military)?;
-        s.serialize_field("mana", &self.mana)?;
-        s.end()
-    }
-}
-
-#[derive(Debug, Clone, JominiDeserialize, Default)]
-#[cfg_attr(feature = "serialize", derive(Serialize))]
-pub struct Eu4Stats {
-    pub countries: Vec<CountryStats>,
-}
-
-impl Serialize for Eu4Stats {
-    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
-    where S: Serializer, {
-        let mut s = serializer.serialize_struct("Eu4Stats", 1)?;
-        s.serialize_field("countries", &self.countries)?;
-        s.end()
-    }
-}
\ No newline at end of file
+use std::collections::BTreeMap;
+
+use jomini::JominiDeserialize;
+use schemars::JsonSchema;
+use serde::Serialize;
+
+// Bump whenever a field is renamed, removed or changes meaning, so consumers of
+// parsed_country.json can tell which layout they were given.
+pub const SCHEMA_VERSION: u32 = 6;
+
+#[derive(Debug, Clone, JominiDeserialize, Default, Serialize, JsonSchema)]
+pub struct CondensedCountry {
+    pub total_development: f32,
+    pub real_development: f32,
+    pub gp_score: i32,
+    pub great_power: bool,
+    pub powers_earned: [i32; 3],
+    pub technology: [i32; 3],
+    pub ideas: Vec<(String, u8)>,
+    pub total_ideas: u8,
+    pub current_manpower: i32,
+    pub max_manpower: i32,
+    pub average_monarch: [f32; 3],
+    pub income: f32,
+    pub income_history: Box<[(u16, i32)]>,
+    pub number_provinces: i32,
+    pub number_buildings: i32,
+    pub buildings_value: i32,
+    pub buildings_per_province: f32,
+    pub innovativeness: f32,
+    pub absolutism: f32,
+    pub average_development: f32,
+    pub average_development_real: f32,
+}
+
+#[derive(Debug, Clone, JominiDeserialize, Default, Serialize, JsonSchema)]
+pub struct CountryMilitary {
+    pub army_tradition: f32,
+    pub army_morale: f32,
+    pub army_discipline: f32,
+    pub army_force_limit: i32,
+    pub army_professionalism: f32,
+    pub siege_ability: f32,
+    pub fort_defense: f32,
+    pub infantry_ability: f32,
+    pub cavalry_ability: f32,
+    pub artillery_ability: f32,
+    pub fire_dealt: f32,
+    pub fire_received: f32,
+    pub shock_dealt: f32,
+    pub shock_received: f32,
+    pub leader_fire: u8,
+    pub leader_shock: u8,
+    pub leader_maneuver: u8,
+    pub leader_siege: u8,
+    pub mercenary_discipline: f32,
+    pub naval_tradition: f32,
+    pub naval_morale: f32,
+    pub naval_force_limit: i32,
+}
+
+#[derive(Debug, Clone, JominiDeserialize, Default, Serialize, JsonSchema)]
+pub struct CountryMana {
+    pub mana_spent: [i32; 3],
+    pub spent_developing: [i32; 3],
+    pub developing_ratio: String,
+    pub spent_tech: i32,
+    pub spent_culture: i32,
+    pub spent_coring: i32,
+    pub spent_inflation: i32,
+    pub spent_ideas: i32,
+    pub spent_force_march: i32,
+    pub spent_generals: i32,
+    pub spent_unjustified: i32,
+}
+
+#[derive(Debug, Clone, JominiDeserialize, Default, Serialize, JsonSchema)]
+pub struct SubjectRelation {
+    pub tag: String,
+    pub subject_type: String,
+    pub start_date: Option<String>,
+}
+
+#[derive(Debug, Clone, JominiDeserialize, Default, Serialize, JsonSchema)]
+pub struct CountrySubjects {
+    pub overlord: Option<String>,
+    pub subject_type: Option<String>,
+    pub subjects: Vec<SubjectRelation>,
+}
+
+// Summed over a country and its direct subjects
+#[derive(Debug, Clone, JominiDeserialize, Default, Serialize, JsonSchema)]
+pub struct BlocStats {
+    pub number_countries: i32,
+    pub total_development: f32,
+    pub income: f32,
+    pub regiments: i32,
+}
+
+#[derive(Debug, Clone, JominiDeserialize, Default, Serialize, JsonSchema)]
+pub struct LargestBattle {
+    pub name: String,
+    pub date: String,
+    pub location: String,
+    pub size: i32,
+}
+
+#[derive(Debug, Clone, JominiDeserialize, Default, Serialize, JsonSchema)]
+pub struct CountryCasualties {
+    pub infantry_combat: i32,
+    pub infantry_attrition: i32,
+    pub cavalry_combat: i32,
+    pub cavalry_attrition: i32,
+    pub artillery_combat: i32,
+    pub artillery_attrition: i32,
+    pub total_land_losses: i32,
+    pub ships_lost: i32,
+    pub battles_won: i32,
+    pub battles_lost: i32,
+    pub largest_battle: Option<LargestBattle>,
+}
+
+#[derive(Debug, Clone, JominiDeserialize, Default, Serialize, JsonSchema)]
+pub struct EstateStats {
+    pub estate: String,
+    pub loyalty: f32,
+    pub influence_modifiers: f32,
+    pub territory: f32,
+}
+
+#[derive(Debug, Clone, JominiDeserialize, Default, Serialize, JsonSchema)]
+pub struct RulerStats {
+    pub name: String,
+    pub start_date: String,
+    pub adm: u16,
+    pub dip: u16,
+    pub mil: u16,
+}
+
+#[derive(Debug, Clone, JominiDeserialize, Default, Serialize, JsonSchema)]
+pub struct CountryRealm {
+    pub religion: Option<String>,
+    pub primary_culture: Option<String>,
+    pub accepted_cultures: Vec<String>,
+    pub religious_unity: Option<f32>,
+    pub tolerance_own: Option<f32>,
+    pub tolerance_heretic: Option<f32>,
+    pub tolerance_heathen: Option<f32>,
+    pub government: Option<String>,
+    pub government_reforms: Vec<String>,
+    pub government_rank: i32,
+    pub estates: Vec<EstateStats>,
+    pub crown_land: f32,
+    pub ruler: Option<RulerStats>,
+}
+
+// Only present for saves with Anbennac enabled
+#[derive(Debug, Clone, JominiDeserialize, Default, Serialize, JsonSchema)]
+pub struct AnbennacStats {
+    pub mage_estate: Option<EstateStats>,
+    pub magic: BTreeMap<String, f32>,
+    pub magic_flags: Vec<String>,
+    pub racial_tolerance: BTreeMap<String, f32>,
+    pub racial_administration: BTreeMap<String, f32>,
+    pub monstrous: bool,
+    pub monstrous_flags: Vec<String>,
+}
+
+#[derive(Debug, Clone, JominiDeserialize, Default, Serialize, JsonSchema)]
+pub struct NodeTrade {
+    pub node: String,
+    pub trade_power: f32,
+    pub power_share: f32,
+    pub income: f32,
+    pub merchant: Option<String>,
+}
+
+#[derive(Debug, Clone, JominiDeserialize, Default, Serialize, JsonSchema)]
+pub struct CountryTrade {
+    pub home_node: Option<String>,
+    pub trade_power: f32,
+    pub trade_income: f32,
+    pub merchants_steering: i32,
+    pub merchants_collecting: i32,
+    pub nodes: Vec<NodeTrade>,
+}
+
+#[derive(Debug, Clone, JominiDeserialize, Default, Serialize, JsonSchema)]
+pub struct CountryStats {
+    pub tag: String,
+    pub name: String,
+    pub player: Option<String>,
+    pub previous_tags: Vec<String>,
+    pub country: CondensedCountry,
+    pub military: CountryMilitary,
+    pub casualties: CountryCasualties,
+    pub mana: CountryMana,
+    pub realm: CountryRealm,
+    pub subjects: CountrySubjects,
+    pub bloc: BlocStats,
+    pub trade: CountryTrade,
+    pub anbennac: Option<AnbennacStats>,
+    // The metrics that aren't stored anywhere else in these stats
+    pub metrics: BTreeMap<String, f32>,
+    // Every metric, for ranking and the other modes. Not written, as most of them are fields above.
+    #[serde(skip)]
+    #[jomini(default)]
+    pub metric_values: BTreeMap<String, f32>,
+    pub ranking: CountryRanking,
+    pub standings: BTreeMap<String, Standing>,
+}
+
+#[derive(Debug, Clone, JominiDeserialize, Default, Serialize, JsonSchema)]
+pub struct TagPeriod {
+    pub tag: String,
+    pub start: Option<String>,
+    pub end: Option<String>,
+}
+
+#[derive(Debug, Clone, JominiDeserialize, Default, Serialize, JsonSchema)]
+pub struct PlayerHistory {
+    pub name: String,
+    pub tag: String,
+    pub playing: bool,
+    // The tags of the nation the player holds now, since the campaign start. The player may have
+    // taken it over at any point, the save doesn't say when.
+    pub nation_tags: Vec<TagPeriod>,
+}
+
+#[derive(Debug, Clone, JominiDeserialize, Default, Serialize, JsonSchema)]
+pub struct SaveMeta {
+    pub file_name: String,
+    pub parsed_at: u64,
+    pub date: String,
+    pub game_version: String,
+    pub checksum: String,
+    pub dlc: Vec<String>,
+    pub mods: Vec<String>,
+    pub ironman: bool,
+    pub multiplayer: bool,
+    pub players: Vec<PlayerHistory>,
+}
+
+// `kind` is one of alliance, royal_marriage, guarantee, military_access, fleet_access, subject,
+// rival or embargo. Alliances and royal marriages are mutual, the rest point from first to second.
+#[derive(Debug, Clone, JominiDeserialize, Default, Serialize, JsonSchema)]
+pub struct DiplomaticRelation {
+    pub kind: String,
+    pub first: String,
+    pub second: String,
+    pub start_date: Option<String>,
+}
+
+#[derive(Debug, Clone, JominiDeserialize, Default, Serialize, JsonSchema)]
+pub struct Opinion {
+    pub from: String,
+    pub to: String,
+    pub opinion: f32,
+}
+
+#[derive(Debug, Clone, JominiDeserialize, Default, Serialize, JsonSchema)]
+pub struct DiplomacyStats {
+    pub relations: Vec<DiplomaticRelation>,
+    pub opinions: Vec<Opinion>,
+}
+
+#[derive(Debug, Clone, JominiDeserialize, Default, Serialize, JsonSchema)]
+pub struct UnitLosses {
+    pub infantry: i32,
+    pub cavalry: i32,
+    pub artillery: i32,
+    pub heavy_ships: i32,
+    pub light_ships: i32,
+    pub galleys: i32,
+    pub transports: i32,
+}
+
+#[derive(Debug, Clone, JominiDeserialize, Default, Serialize, JsonSchema)]
+pub struct WarParticipant {
+    pub tag: String,
+    pub side: String,
+    pub participation: f32,
+    pub losses: UnitLosses,
+}
+
+#[derive(Debug, Clone, JominiDeserialize, Default, Serialize, JsonSchema)]
+pub struct BattleSide {
+    pub country: String,
+    pub commander: Option<String>,
+    pub infantry: i32,
+    pub cavalry: i32,
+    pub artillery: i32,
+    pub ships: i32,
+    pub losses: i32,
+}
+
+#[derive(Debug, Clone, JominiDeserialize, Default, Serialize, JsonSchema)]
+pub struct BattleReport {
+    pub name: String,
+    pub date: String,
+    pub location: String,
+    pub attacker_won: bool,
+    pub attacker: BattleSide,
+    pub defender: BattleSide,
+}
+
+#[derive(Debug, Clone, JominiDeserialize, Default, Serialize, JsonSchema)]
+pub struct WarReport {
+    pub name: String,
+    pub active: bool,
+    pub start_date: Option<String>,
+    pub end_date: Option<String>,
+    pub casus_belli: Option<String>,
+    pub war_score: Option<f32>,
+    pub attacker_leader: String,
+    pub defender_leader: String,
+    pub participants: Vec<WarParticipant>,
+    pub battles: Vec<BattleReport>,
+}
+
+#[derive(Debug, Clone, JominiDeserialize, Default, Serialize, JsonSchema)]
+pub struct TradeNodeCountry {
+    pub tag: String,
+    pub trade_power: f32,
+    pub power_share: f32,
+    pub income: f32,
+    pub merchant: Option<String>,
+    pub home_node: bool,
+}
+
+#[derive(Debug, Clone, JominiDeserialize, Default, Serialize, JsonSchema)]
+pub struct TradeNodeStats {
+    pub name: String,
+    pub value: f32,
+    pub local_value: f32,
+    pub total_power: f32,
+    pub countries: Vec<TradeNodeCountry>,
+}
+
+#[derive(Debug, Clone, JominiDeserialize, Default, Serialize, JsonSchema)]
+pub struct MetricDefinition {
+    pub key: String,
+    pub category: String,
+    pub label: String,
+    pub unit: String,
+    // Where each country's value is, e.g. `country.technology[0]`. Without one it's in the
+    // country's `metrics` map.
+    pub path: Option<String>,
+    // Ranks and percentiles put the lowest value first
+    pub lower_is_better: bool,
+}
+
+#[derive(Debug, Clone, JominiDeserialize, Default, Serialize, JsonSchema)]
+pub struct RankingInput {
+    pub metric: String,
+    pub weight: f32,
+}
+
+#[derive(Debug, Clone, JominiDeserialize, Default, Serialize, JsonSchema)]
+pub struct RankingCategory {
+    pub name: String,
+    pub weight: f32,
+    pub inputs: Vec<RankingInput>,
+}
+
+// The weights behind the power ranking, also read from `--ranking <config.json>`
+#[derive(Debug, Clone, JominiDeserialize, Default, Serialize, JsonSchema)]
+pub struct RankingConfig {
+    pub categories: Vec<RankingCategory>,
+}
+
+#[derive(Debug, Clone, JominiDeserialize, Default, Serialize, JsonSchema)]
+pub struct CategoryScore {
+    pub category: String,
+    pub score: f32,
+    pub rank: usize,
+}
+
+// Scores go from 0 to 100, ranks start at 1
+#[derive(Debug, Clone, JominiDeserialize, Default, Serialize, JsonSchema)]
+pub struct CountryRanking {
+    pub score: f32,
+    pub rank: usize,
+    pub categories: Vec<CategoryScore>,
+}
+
+// Where a country stands in one metric. Rank 1 has the best value, the highest one or, for metrics
+// that are `lower_is_better`, the lowest. The percentile is the share of the others with a worse
+// value. The player columns are only set for players.
+#[derive(Debug, Clone, JominiDeserialize, Default, Serialize, JsonSchema)]
+pub struct Standing {
+    pub rank: usize,
+    pub percentile: f32,
+    pub player_rank: Option<usize>,
+    pub player_percentile: Option<f32>,
+}
+
+#[derive(Debug, Clone, JominiDeserialize, Default, Serialize, JsonSchema)]
+pub struct Eu4Stats {
+    pub schema_version: u32,
+    pub meta: SaveMeta,
+    pub countries: Vec<CountryStats>,
+    pub diplomacy: DiplomacyStats,
+    pub wars: Vec<WarReport>,
+    pub trade: Vec<TradeNodeStats>,
+    pub metrics: Vec<MetricDefinition>,
+    pub ranking: RankingConfig,
+    // Every country with development in the save, selected or not
+    pub existing_tags: Vec<String>,
+}
+
+impl Eu4Stats {
+    // Finds the entry for the nation of `country` in this (older) save, by its current tag or by
+    // the tags it had before. Nations are matched whoever plays them, so a player who switched
+    // nation isn't compared with the one they left.
+    pub fn find_previous(&self, country: &CountryStats) -> Option<&CountryStats> {
+        return self.countries.iter().find(|c| c.tag == country.tag)
+            .or_else(|| self.countries.iter().find(|c| country.previous_tags.contains(&c.tag)));
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, JsonSchema)]
+pub struct CountryDelta {
+    pub tag: String,
+    pub name: String,
+    pub player: Option<String>,
+    pub country: CondensedCountry,
+    pub old_country: Option<CondensedCountry>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, JsonSchema)]
+pub struct SaveSummary {
+    pub id: usize,
+    pub file_name: String,
+    pub date: String,
+    pub parsed_at: u64,
+    pub number_countries: usize,
+}
+
+#[derive(Debug, Clone, Default, Serialize, JsonSchema)]
+pub struct GraphNode {
+    pub id: String,
+    pub name: String,
+    pub player: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, JsonSchema)]
+pub struct GraphEdge {
+    pub source: String,
+    pub target: String,
+    pub kind: String,
+    pub directed: bool,
+}
+
+#[derive(Debug, Clone, Default, Serialize, JsonSchema)]
+pub struct DiplomacyGraph {
+    pub nodes: Vec<GraphNode>,
+    pub edges: Vec<GraphEdge>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, JsonSchema)]
+pub struct ModifierDump {
+    pub name: String,
+    pub expires: Option<String>,
+    pub permanent: bool,
+}
+
+// Flags map to the date they were set
+#[derive(Debug, Clone, Default, Serialize, JsonSchema)]
+pub struct CountryDump {
+    pub tag: String,
+    pub name: String,
+    pub player: Option<String>,
+    pub flags: BTreeMap<String, String>,
+    pub hidden_flags: BTreeMap<String, String>,
+    pub variables: BTreeMap<String, f32>,
+    pub triggered_modifiers: Vec<String>,
+    pub event_modifiers: Vec<ModifierDump>,
+    pub active_policies: Vec<String>,
+}
+
+// A milestone a country reached between two saves of a campaign. `first` is set when no country
+// had reached it before, including those reaching it in the same session.
+#[derive(Debug, Clone, Default, Serialize, JsonSchema)]
+pub struct MilestoneEvent {
+    pub date: String,
+    pub previous_date: String,
+    pub tag: String,
+    pub name: String,
+    pub player: Option<String>,
+    pub kind: String,
+    pub threshold: Option<f32>,
+    pub value: Option<f32>,
+    pub first: bool,
+    pub description: String,
+}
@@ -0,0 +1,20 @@
+// State that only exists when a particular mod is enabled. Each mod gets an extractor that is
+// picked from the mods listed in the save, and fills in its own optional section of CountryStats.
+// Support for another mod is a new extractor added to `get_extractors`.
+use crate::anbennac::Anbennac;
+use crate::extras::ExtraCountry;
+use crate::models;
+
+pub trait ModExtractor {
+    // `mods` holds the names of the mods enabled in the save
+    fn applies(&self, mods: &[String]) -> bool;
+
+    fn extract(&self, extra: Option<&ExtraCountry>, stats: &mut models::CountryStats);
+}
+
+pub fn get_extractors(mods: &[String]) -> Vec<Box<dyn ModExtractor>> {
+    let all: Vec<Box<dyn ModExtractor>> = vec![
+        Box::new(Anbennac),
+    ];
+    return all.into_iter().filter(|e| e.applies(mods)).collect();
+}
@@ -0,0 +1,247 @@
+// The power ranking: every input metric is normalised to 0-100 across the ranked countries (the
+// lowest value scores 0, the highest 100, and all of them 50 when they are equal), a category score
+// is the weighted average of its inputs and the overall score the weighted average of the
+// categories. Inputs a country doesn't have are left out of its averages rather than counted as 0.
+// For metrics where lower is better, such as inflation, the lowest value scores 100.
+use log::info;
+use std::collections::{BTreeMap, HashSet};
+use std::error::Error;
+use std::fs::File;
+use std::io::BufReader;
+use std::path::Path;
+use std::result::Result;
+
+use crate::metrics::MetricRegistry;
+use crate::models::{CategoryScore, CountryRanking, CountryStats, MetricDefinition, RankingCategory, RankingConfig, RankingInput};
+
+fn category(name: &str, weight: f32, inputs: &[(&str, f32)]) -> RankingCategory {
+    return RankingCategory {
+        name: name.to_string(),
+        weight,
+        inputs: inputs.iter().map(|(metric, weight)| RankingInput { metric: metric.to_string(), weight: *weight }).collect(),
+    };
+}
+
+pub fn default_config() -> RankingConfig {
+    return RankingConfig {
+        categories: vec![
+            category("economy", 1.0, &[
+                ("country.income", 1.0),
+                ("country.buildings_value", 0.5),
+                ("trade.trade_income", 0.5),
+            ]),
+            category("military", 1.0, &[
+                ("military.army_force_limit", 1.0),
+                ("military.army_discipline", 0.5),
+                ("country.mil_tech", 0.5),
+                ("country.max_manpower", 0.5),
+            ]),
+            category("development", 1.0, &[
+                ("country.total_development", 1.0),
+                ("country.gp_score", 0.5),
+                ("country.adm_tech", 0.25),
+                ("country.dip_tech", 0.25),
+            ]),
+            category("mana_efficiency", 0.5, &[
+                ("mana.efficiency", 1.0),
+            ]),
+        ],
+    };
+}
+
+// Every input has to be a metric of `registry`, so a typo doesn't silently score 0
+fn validate(config: &RankingConfig, registry: &MetricRegistry) -> Result<(), Box<dyn Error>> {
+    let keys: HashSet<String> = registry.definitions().into_iter().map(|d| d.key).collect();
+    for category in &config.categories {
+        for input in &category.inputs {
+            if !keys.contains(&input.metric) {
+                return Err(format!("Unknown metric {:?} in ranking category {:?}", input.metric, category.name).into());
+            }
+        }
+    }
+    return Ok(());
+}
+
+pub fn load_config<P>(path: P, registry: &MetricRegistry) -> Result<RankingConfig, Box<dyn Error>>
+where P: AsRef<Path> {
+    let config: RankingConfig = serde_json::from_reader(BufReader::new(File::open(&path)?))?;
+    validate(&config, registry)?;
+    info!("Loaded {} ranking categories from {:?}", config.categories.len(), path.as_ref());
+    return Ok(config);
+}
+
+// `--ranking <config.json>` replaces the default weights. Removes the flag from `args` like
+// `Selection::from_args`.
+pub fn config_from_args(args: &mut Vec<String>, registry: &MetricRegistry) -> Result<RankingConfig, Box<dyn Error>> {
+    let Some(i) = args.iter().position(|a| a == "--ranking") else {
+        return Ok(default_config());
+    };
+    if i + 1 >= args.len() {
+        return Err(format!("Missing value for {}", args[i]).into());
+    }
+    let config = load_config(args.remove(i + 1), registry)?;
+    args.remove(i);
+    return Ok(config);
+}
+
+fn round(score: f32) -> f32 {
+    return (score * 10.0).round() / 10.0;
+}
+
+fn weighted_average(values: impl Iterator<Item = (f32, f32)>) -> Option<f32> {
+    let (sum, weights) = values.fold((0.0, 0.0), |(sum, weights), (value, weight)| (sum + value * weight, weights + weight));
+    if weights <= 0.0 {
+        return None;
+    }
+    return Some(sum / weights);
+}
+
+// 1 for the highest score, equal scores share a rank (1, 2, 2, 4)
+pub fn get_ranks(scores: &[f32]) -> Vec<usize> {
+    return scores.iter()
+        .map(|score| scores.iter().filter(|other| *other > score).count() + 1)
+        .collect();
+}
+
+// Scores each of `countries` from its metrics, relative to the others
+pub fn rank_countries(countries: &mut [CountryStats], config: &RankingConfig, definitions: &[MetricDefinition]) {
+    let lower_is_better: HashSet<&str> = definitions.iter().filter(|d| d.lower_is_better).map(|d| d.key.as_str()).collect();
+    let mut ranges: BTreeMap<&str, (f32, f32)> = BTreeMap::new();
+    for input in config.categories.iter().flat_map(|c| &c.inputs) {
+        for country in countries.iter() {
+            if let Some(value) = country.metric_values.get(&input.metric) {
+                let range = ranges.entry(&input.metric).or_insert((*value, *value));
+                *range = (range.0.min(*value), range.1.max(*value));
+            }
+        }
+    }
+    let normalise = |metric: &str, value: f32| -> f32 {
+        let (min, max) = ranges[metric];
+        if max - min <= f32::EPSILON {
+            return 50.0;
+        }
+        let score = (value - min) / (max - min) * 100.0;
+        if lower_is_better.contains(metric) {
+            return 100.0 - score;
+        }
+        return score;
+    };
+
+    // Per country, the score of each category, `None` when it has none of its inputs
+    let category_scores: Vec<Vec<Option<f32>>> = countries.iter()
+        .map(|country| config.categories.iter()
+            .map(|category| weighted_average(category.inputs.iter().filter_map(|input| {
+                country.metric_values.get(&input.metric).map(|value| (normalise(&input.metric, *value), input.weight))
+            })))
+            .collect())
+        .collect();
+    let scores: Vec<f32> = category_scores.iter()
+        .map(|scores| weighted_average(config.categories.iter().zip(scores)
+            .filter_map(|(category, score)| score.map(|s| (s, category.weight)))).unwrap_or(0.0))
+        .collect();
+
+    let ranks = get_ranks(&scores);
+    let category_ranks: Vec<Vec<usize>> = (0..config.categories.len())
+        .map(|i| get_ranks(&category_scores.iter().map(|scores| scores[i].unwrap_or(0.0)).collect::<Vec<f32>>()))
+        .collect();
+    for (i, country) in countries.iter_mut().enumerate() {
+        country.ranking = CountryRanking {
+            score: round(scores[i]),
+            rank: ranks[i],
+            categories: config.categories.iter().enumerate()
+                .map(|(c, category)| CategoryScore {
+                    category: category.name.clone(),
+                    score: round(category_scores[i][c].unwrap_or(0.0)),
+                    rank: category_ranks[c][i],
+                })
+                .collect(),
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn country(values: &[(&str, f32)]) -> CountryStats {
+        return CountryStats {
+            metric_values: values.iter().map(|(key, value)| (key.to_string(), *value)).collect(),
+            ..Default::default()
+        };
+    }
+
+    fn config(categories: Vec<RankingCategory>) -> RankingConfig {
+        return RankingConfig { categories };
+    }
+
+    fn get_scores(mut countries: Vec<CountryStats>, config: &RankingConfig) -> Vec<f32> {
+        rank_countries(&mut countries, config, &[]);
+        return countries.iter().map(|c| c.ranking.score).collect();
+    }
+
+    #[test]
+    fn ranks() {
+        let cases: [(&[f32], &[usize]); 4] = [
+            (&[], &[]),
+            (&[5.0], &[1]),
+            (&[3.0, 1.0, 3.0, 2.0], &[1, 4, 1, 3]),
+            (&[2.0, 2.0, 2.0], &[1, 1, 1]),
+        ];
+        for (values, expected) in cases {
+            assert_eq!(get_ranks(values), expected, "{:?}", values);
+        }
+    }
+
+    #[test]
+    fn scores() {
+        let one = config(vec![category("c", 1.0, &[("a", 1.0)])]);
+        let inputs = config(vec![category("c", 1.0, &[("a", 1.0), ("b", 3.0)])]);
+        let categories = config(vec![category("x", 1.0, &[("a", 1.0)]), category("y", 3.0, &[("b", 1.0)])]);
+        let equal = config(vec![category("c", 1.0, &[("a", 1.0), ("b", 1.0)])]);
+        let equal_categories = config(vec![category("x", 1.0, &[("a", 1.0)]), category("y", 1.0, &[("b", 1.0)])]);
+        let opposite = || vec![country(&[("a", 1.0), ("b", 0.0)]), country(&[("a", 0.0), ("b", 1.0)])];
+        let missing = || vec![country(&[("a", 10.0), ("b", 0.0)]), country(&[("a", 0.0), ("b", 10.0)]), country(&[("a", 10.0)])];
+        let cases = [
+            // Scaled from the lowest to the highest value
+            (&one, vec![country(&[("a", 10.0)]), country(&[("a", 20.0)]), country(&[("a", 40.0)])], vec![0.0, 33.3, 100.0]),
+            // Everyone equal scores the middle
+            (&one, vec![country(&[("a", 5.0)]), country(&[("a", 5.0)])], vec![50.0, 50.0]),
+            (&one, vec![country(&[("a", 5.0)])], vec![50.0]),
+            (&inputs, opposite(), vec![25.0, 75.0]),
+            (&categories, opposite(), vec![25.0, 75.0]),
+            // A missing input is left out of the average rather than counted as 0
+            (&equal, missing(), vec![50.0, 50.0, 100.0]),
+            // And so is a category without any of its inputs
+            (&equal_categories, missing(), vec![50.0, 50.0, 100.0]),
+            (&one, vec![country(&[("a", 5.0)]), country(&[])], vec![50.0, 0.0]),
+        ];
+        for (config, countries, expected) in cases {
+            assert_eq!(get_scores(countries, config), expected, "{:?}", config);
+        }
+    }
+
+    #[test]
+    fn ranks_and_categories() {
+        let config = config(vec![category("x", 1.0, &[("a", 1.0)]), category("y", 1.0, &[("b", 1.0)])]);
+        let mut countries = vec![country(&[("a", 10.0), ("b", 0.0)]), country(&[("a", 0.0), ("b", 10.0)]), country(&[("a", 10.0)])];
+        rank_countries(&mut countries, &config, &[]);
+        assert_eq!(countries.iter().map(|c| c.ranking.rank).collect::<Vec<_>>(), [2, 2, 1]);
+
+        let categories: Vec<(&str, f32, usize)> = countries[2].ranking.categories.iter()
+            .map(|c| (c.category.as_str(), c.score, c.rank))
+            .collect();
+        assert_eq!(categories, [("x", 100.0, 1), ("y", 0.0, 2)]);
+        assert_eq!(countries[1].ranking.categories[0].rank, 3);
+        assert_eq!(countries[1].ranking.categories[1].rank, 1);
+    }
+
+    #[test]
+    fn lower_is_better() {
+        let config = config(vec![category("c", 1.0, &[("inflation", 1.0)])]);
+        let definitions = [MetricDefinition { key: "inflation".to_string(), lower_is_better: true, ..Default::default() }];
+        let mut countries = vec![country(&[("inflation", 2.0)]), country(&[("inflation", 10.0)]), country(&[("inflation", 4.0)])];
+        rank_countries(&mut countries, &config, &definitions);
+        assert_eq!(countries.iter().map(|c| c.ranking.score).collect::<Vec<_>>(), [100.0, 0.0, 75.0]);
+        assert_eq!(countries.iter().map(|c| c.ranking.rank).collect::<Vec<_>>(), [1, 3, 2]);
+    }
+}
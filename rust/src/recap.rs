@@ -0,0 +1,371 @@
+// A human-readable recap of what happened between two saves of a campaign, as Markdown or HTML:
+// development gainers and losers, wars, annexations, each player's progress, income changes and
+// new rulers. Countries are matched between the saves with `Eu4Stats::find_previous`.
+use log::info;
+use std::error::Error;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+use std::result::Result;
+
+use crate::models::{CountryStats, Eu4Stats, WarReport};
+
+const NUM_MOVERS: usize = 5;
+
+enum Block {
+    Text(String),
+    List(Vec<String>),
+    Table(Vec<String>, Vec<Vec<String>>),
+}
+
+struct Section {
+    title: String,
+    blocks: Vec<Block>,
+}
+
+fn get_name(stats: &Eu4Stats, tag: &str) -> String {
+    return stats.countries.iter().find(|c| c.tag == tag).map(|c| c.name.clone()).unwrap_or(tag.to_string());
+}
+
+// "Lorent (Alice)" for players, the country name for everyone else
+pub fn get_label(country: &CountryStats) -> String {
+    return match &country.player {
+        Some(player) => format!("{} ({})", country.name, player),
+        None => country.name.clone(),
+    };
+}
+
+fn signed(value: f32) -> String {
+    return format!("{:+.1}", value);
+}
+
+// Each country of `current` that was already in `previous`, with its old entry
+fn get_pairs<'a>(current: &'a Eu4Stats, previous: &'a Eu4Stats) -> Vec<(&'a CountryStats, &'a CountryStats)> {
+    return current.countries.iter()
+        .filter_map(|c| previous.find_previous(c).map(|p| (c, p)))
+        .collect();
+}
+
+// The largest rises and the largest falls of `value`, as two lists
+fn get_movers<F>(pairs: &[(&CountryStats, &CountryStats)], value: F, unit: &str) -> (Vec<String>, Vec<String>)
+where F: Fn(&CountryStats) -> f32 {
+    let mut changes: Vec<(&CountryStats, f32)> = pairs.iter().map(|(c, p)| (*c, value(c) - value(p))).collect();
+    changes.sort_by(|(_a, a), (_b, b)| b.total_cmp(a));
+    let describe = |(c, change): &(&CountryStats, f32)| format!("{}: {} {} (now {:.1})", get_label(c), signed(*change), unit, value(c));
+    let gainers = changes.iter().filter(|(_c, change)| *change > 0.0).take(NUM_MOVERS).map(describe).collect();
+    let losers = changes.iter().rev().filter(|(_c, change)| *change < 0.0).take(NUM_MOVERS).map(describe).collect();
+    return (gainers, losers);
+}
+
+fn movers_section(title: &str, movers: (Vec<String>, Vec<String>)) -> Section {
+    let (gainers, losers) = movers;
+    let mut blocks = Vec::new();
+    if !gainers.is_empty() {
+        blocks.push(Block::Text("Biggest gains:".to_string()));
+        blocks.push(Block::List(gainers));
+    }
+    if !losers.is_empty() {
+        blocks.push(Block::Text("Biggest losses:".to_string()));
+        blocks.push(Block::List(losers));
+    }
+    if blocks.is_empty() {
+        blocks.push(Block::Text("No changes.".to_string()));
+    }
+    return Section { title: title.to_string(), blocks };
+}
+
+// War names repeat over a campaign, their start dates tell them apart
+fn same_war(a: &WarReport, b: &WarReport) -> bool {
+    return a.name == b.name && a.start_date == b.start_date;
+}
+
+fn wars_section(current: &Eu4Stats, previous: &Eu4Stats) -> Section {
+    let describe = |stats: &Eu4Stats, war: &WarReport| {
+        format!("{}: {} against {}{}", war.name, get_name(stats, &war.attacker_leader), get_name(stats, &war.defender_leader),
+            war.casus_belli.as_ref().map(|cb| format!(" ({})", cb)).unwrap_or_default())
+    };
+    let started: Vec<String> = current.wars.iter()
+        .filter(|w| !previous.wars.iter().any(|p| same_war(p, w)))
+        .filter(|w| w.start_date.as_ref().is_none_or(|d| *d > previous.meta.date))
+        .map(|w| describe(current, w))
+        .collect();
+    // Wars that were going on and are now over or gone from the save
+    let ended: Vec<String> = previous.wars.iter()
+        .filter(|p| p.active)
+        .filter(|p| !current.wars.iter().any(|w| same_war(p, w) && w.active))
+        .map(|p| describe(previous, p))
+        .collect();
+
+    let mut blocks = Vec::new();
+    for (title, wars) in [("Started:", started), ("Ended:", ended)] {
+        if !wars.is_empty() {
+            blocks.push(Block::Text(title.to_string()));
+            blocks.push(Block::List(wars));
+        }
+    }
+    if blocks.is_empty() {
+        blocks.push(Block::Text("No wars started or ended.".to_string()));
+    }
+    return Section { title: "Wars".to_string(), blocks };
+}
+
+// Countries of `previous` that are gone from the save and that no country of `current` continues.
+// Countries that merely dropped out of the selection still have development in the save.
+fn annexed_section(current: &Eu4Stats, previous: &Eu4Stats) -> Section {
+    let continued: Vec<&str> = current.countries.iter()
+        .filter_map(|c| previous.find_previous(c))
+        .map(|p| p.tag.as_str())
+        .collect();
+    let annexed: Vec<String> = previous.countries.iter()
+        .filter(|p| !current.existing_tags.contains(&p.tag))
+        .filter(|p| !continued.contains(&p.tag.as_str()))
+        .map(|p| {
+            let overlord = previous.diplomacy.relations.iter()
+                .find(|r| r.kind == "subject" && r.second == p.tag)
+                .map(|r| format!(", subject of {}", get_name(previous, &r.first)))
+                .unwrap_or_default();
+            format!("{} ({:.0} development{})", get_label(p), p.country.total_development, overlord)
+        })
+        .collect();
+    let blocks = if annexed.is_empty() {
+        vec![Block::Text("No country disappeared.".to_string())]
+    } else {
+        vec![Block::List(annexed)]
+    };
+    return Section { title: "Annexed".to_string(), blocks };
+}
+
+fn players_section(pairs: &[(&CountryStats, &CountryStats)]) -> Section {
+    let headers = ["Player", "Country", "Development", "Income", "Tech", "Ideas", "New idea groups"]
+        .iter().map(|h| h.to_string()).collect();
+    let mut rows = Vec::new();
+    for (c, p) in pairs.iter().filter(|(c, _p)| c.player.is_some()) {
+        let tech = |t: &[i32; 3]| format!("{}/{}/{}", t[0], t[1], t[2]);
+        let new_groups: Vec<&str> = c.country.ideas.iter()
+            .filter(|(name, _level)| !p.country.ideas.iter().any(|(old, _)| old == name))
+            .map(|(name, _level)| name.as_str())
+            .collect();
+        rows.push(vec![
+            c.player.clone().unwrap_or_default(),
+            c.name.clone(),
+            format!("{:.0} ({})", c.country.total_development, signed(c.country.total_development - p.country.total_development)),
+            format!("{:.1} ({})", c.country.income, signed(c.country.income - p.country.income)),
+            format!("{} → {}", tech(&p.country.technology), tech(&c.country.technology)),
+            format!("{} ({:+})", c.country.total_ideas, c.country.total_ideas as i32 - p.country.total_ideas as i32),
+            new_groups.join(", "),
+        ]);
+    }
+    let blocks = if rows.is_empty() {
+        vec![Block::Text("No players.".to_string())]
+    } else {
+        vec![Block::Table(headers, rows)]
+    };
+    return Section { title: "Players".to_string(), blocks };
+}
+
+// New rulers of players and great powers
+fn rulers_section(pairs: &[(&CountryStats, &CountryStats)]) -> Section {
+    let rulers: Vec<String> = pairs.iter()
+        .filter(|(c, _p)| c.player.is_some() || c.country.great_power)
+        .filter_map(|(c, p)| {
+            let ruler = c.realm.ruler.as_ref()?;
+            let old_ruler = p.realm.ruler.as_ref();
+            if old_ruler.is_some_and(|o| o.name == ruler.name && o.start_date == ruler.start_date) {
+                return None;
+            }
+            let predecessor = old_ruler.map(|o| format!(", succeeding {}", o.name)).unwrap_or_default();
+            Some(format!("{}: {} ({}/{}/{}) since {}{}", get_label(c), ruler.name, ruler.adm, ruler.dip, ruler.mil, ruler.start_date, predecessor))
+        })
+        .collect();
+    let blocks = if rulers.is_empty() {
+        vec![Block::Text("No new rulers.".to_string())]
+    } else {
+        vec![Block::List(rulers)]
+    };
+    return Section { title: "New rulers".to_string(), blocks };
+}
+
+fn generate_sections(current: &Eu4Stats, previous: &Eu4Stats) -> Vec<Section> {
+    let pairs = get_pairs(current, previous);
+    return vec![
+        movers_section("Development", get_movers(&pairs, |c| c.country.total_development, "development")),
+        wars_section(current, previous),
+        annexed_section(current, previous),
+        players_section(&pairs),
+        movers_section("Income", get_movers(&pairs, |c| c.country.income, "ducats")),
+        rulers_section(&pairs),
+    ];
+}
+
+fn get_title(current: &Eu4Stats, previous: &Eu4Stats) -> String {
+    return format!("Session recap: {} to {}", previous.meta.date, current.meta.date);
+}
+
+pub fn generate_markdown(current: &Eu4Stats, previous: &Eu4Stats) -> String {
+    let mut out = format!("# {}\n", get_title(current, previous));
+    for section in generate_sections(current, previous) {
+        out += &format!("\n## {}\n", section.title);
+        for block in section.blocks {
+            out += "\n";
+            match block {
+                Block::Text(text) => out += &format!("{}\n", text),
+                Block::List(items) => {
+                    for item in items {
+                        out += &format!("- {}\n", item);
+                    }
+                }
+                Block::Table(headers, rows) => {
+                    out += &format!("| {} |\n", headers.join(" | "));
+                    out += &format!("|{}\n", "---|".repeat(headers.len()));
+                    for row in rows {
+                        out += &format!("| {} |\n", row.iter().map(|c| c.replace('|', "\\|")).collect::<Vec<_>>().join(" | "));
+                    }
+                }
+            }
+        }
+    }
+    return out;
+}
+
+pub fn escape(text: &str) -> String {
+    return text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;");
+}
+
+pub fn generate_html(current: &Eu4Stats, previous: &Eu4Stats) -> String {
+    let title = escape(&get_title(current, previous));
+    let mut out = format!("<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>{}</title>\n<style>table {{ border-collapse: collapse; }} th, td {{ border: 1px solid #ccc; padding: 2px 6px; }}</style>\n</head>\n<body>\n<h1>{}</h1>\n", title, title);
+    for section in generate_sections(current, previous) {
+        out += &format!("<h2>{}</h2>\n", escape(&section.title));
+        for block in section.blocks {
+            match block {
+                Block::Text(text) => out += &format!("<p>{}</p>\n", escape(&text)),
+                Block::List(items) => {
+                    out += "<ul>\n";
+                    for item in items {
+                        out += &format!("<li>{}</li>\n", escape(&item));
+                    }
+                    out += "</ul>\n";
+                }
+                Block::Table(headers, rows) => {
+                    out += "<table>\n<tr>";
+                    for header in headers {
+                        out += &format!("<th>{}</th>", escape(&header));
+                    }
+                    out += "</tr>\n";
+                    for row in rows {
+                        out += "<tr>";
+                        for cell in row {
+                            out += &format!("<td>{}</td>", escape(&cell));
+                        }
+                        out += "</tr>\n";
+                    }
+                    out += "</table>\n";
+                }
+            }
+        }
+    }
+    out += "</body>\n</html>\n";
+    return out;
+}
+
+// HTML when `recap_path` ends in .html or .htm, Markdown otherwise
+pub fn write_recap<P>(current: &Eu4Stats, previous: &Eu4Stats, recap_path: P) -> Result<(), Box<dyn Error>>
+where P: AsRef<Path> {
+    let html = recap_path.as_ref().extension().is_some_and(|e| e == "html" || e == "htm");
+    let recap = if html { generate_html(current, previous) } else { generate_markdown(current, previous) };
+    let mut writer = BufWriter::new(File::create(&recap_path)?);
+    writer.write_all(recap.as_bytes())?;
+    writer.flush()?;
+    info!("Finished writing recap to {:?}", recap_path.as_ref());
+    return Ok(());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{CondensedCountry, SaveMeta};
+
+    fn country(tag: &str, player: Option<&str>, development: f32, income: f32) -> CountryStats {
+        return CountryStats {
+            tag: tag.to_string(),
+            name: tag.to_string(),
+            player: player.map(|p| p.to_string()),
+            country: CondensedCountry { total_development: development, income, ..Default::default() },
+            ..Default::default()
+        };
+    }
+
+    fn save(date: &str, countries: Vec<CountryStats>, existing_tags: &[&str]) -> Eu4Stats {
+        return Eu4Stats {
+            meta: SaveMeta { date: date.to_string(), ..Default::default() },
+            countries,
+            existing_tags: existing_tags.iter().map(|t| t.to_string()).collect(),
+            ..Default::default()
+        };
+    }
+
+    fn get_lists(section: Section) -> Vec<Vec<String>> {
+        return section.blocks.into_iter().filter_map(|b| match b {
+            Block::List(items) => Some(items),
+            _ => None,
+        }).collect();
+    }
+
+    fn with_progress(mut country: CountryStats, technology: [i32; 3], ideas: &[(&str, u8)]) -> CountryStats {
+        country.country.technology = technology;
+        country.country.ideas = ideas.iter().map(|(name, level)| (name.to_string(), *level)).collect();
+        country.country.total_ideas = ideas.iter().map(|(_name, level)| level).sum();
+        return country;
+    }
+
+    // Alice moved from A01 to the larger B01, and A01 was annexed since
+    fn player_swap() -> (Eu4Stats, Eu4Stats) {
+        let previous = save("1450.1.1", vec![
+            country("A01", Some("Alice"), 400.0, 10.0),
+            with_progress(country("B01", None, 1200.0, 30.0), [10, 10, 10], &[("trade_ideas", 7)]),
+            country("C01", None, 100.0, 5.0),
+        ], &["A01", "B01", "C01"]);
+        let current = save("1460.1.1", vec![
+            with_progress(country("B01", Some("Alice"), 1260.0, 31.0), [11, 10, 12], &[("trade_ideas", 7), ("quantity_ideas", 2)]),
+            country("C01", None, 150.0, 4.0),
+        ], &["B01", "C01"]);
+        return (current, previous);
+    }
+
+    #[test]
+    fn movers() {
+        let (current, previous) = player_swap();
+        let pairs = get_pairs(&current, &previous);
+        let development = get_lists(movers_section("Development", get_movers(&pairs, |c| c.country.total_development, "development")));
+        assert_eq!(development, [[
+            "B01 (Alice): +60.0 development (now 1260.0)",
+            "C01: +50.0 development (now 150.0)",
+        ]]);
+        let income = get_lists(movers_section("Income", get_movers(&pairs, |c| c.country.income, "ducats")));
+        assert_eq!(income, [["B01 (Alice): +1.0 ducats (now 31.0)"], ["C01: -1.0 ducats (now 4.0)"]]);
+    }
+
+    #[test]
+    fn players() {
+        let (current, previous) = player_swap();
+        let Block::Table(_headers, rows) = players_section(&get_pairs(&current, &previous)).blocks.remove(0) else {
+            panic!("No players table");
+        };
+        assert_eq!(rows, [["Alice", "B01", "1260 (+60.0)", "31.0 (+1.0)", "10/10/10 → 11/10/12", "9 (+2)", "quantity_ideas"]]);
+    }
+
+    #[test]
+    fn annexed() {
+        let (current, previous) = player_swap();
+        assert_eq!(get_lists(annexed_section(&current, &previous)), [["A01 (Alice) (400 development)"]]);
+
+        let previous = save("1450.1.1", vec![country("A01", None, 10.0, 1.0), country("B01", None, 10.0, 1.0), country("C01", None, 10.0, 1.0), country("D01", None, 10.0, 1.0)], &["A01", "B01", "C01", "D01"]);
+        let current = save("1460.1.1", vec![
+            country("A01", None, 10.0, 1.0),
+            // D01 formed E01
+            CountryStats { previous_tags: vec!["D01".to_string()], ..country("E01", None, 10.0, 1.0) },
+        ], &["A01", "C01", "E01"]);
+        // C01 is still in the save, it only fell out of the selection (`--top`, `--min-development`)
+        assert_eq!(get_lists(annexed_section(&current, &previous)), [["B01 (10 development)"]]);
+    }
+}
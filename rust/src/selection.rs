@@ -0,0 +1,254 @@
+use std::collections::{HashMap, HashSet};
+use std::error::Error;
+use std::result::Result;
+
+use eu4save::CountryTag;
+use eu4save::models::Country;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RankBy {
+    Development,
+    GreatPowerScore,
+}
+
+// Which countries end up in the output. `players`, `tags`, `great_powers` and `top` each select
+// countries and are combined, so `--players --top 20` gives the players plus the 20 largest
+// countries. With none of them every country is selected. `exclude_subjects` and
+// `min_development` then filter the selection. Countries without development (dead tags and tags
+// that never existed) are always left out.
+#[derive(Debug, Clone)]
+pub struct Selection {
+    pub players: bool,
+    pub tags: Vec<String>,
+    pub great_powers: bool,
+    pub top: Option<usize>,
+    pub top_by: RankBy,
+    pub exclude_subjects: bool,
+    pub min_development: Option<f32>,
+}
+
+impl Default for Selection {
+    fn default() -> Self {
+        Selection {
+            players: false,
+            tags: Vec::new(),
+            great_powers: false,
+            top: None,
+            top_by: RankBy::Development,
+            exclude_subjects: false,
+            min_development: None,
+        }
+    }
+}
+
+// What the selection reads from a country. It is implemented for eu4save's `Country`, and for a
+// small struct in the tests.
+pub trait SelectionInput {
+    fn development(&self) -> f32;
+    fn great_power_score(&self) -> f32;
+    fn is_subject(&self) -> bool;
+}
+
+impl SelectionInput for Country {
+    fn development(&self) -> f32 {
+        return self.raw_development;
+    }
+
+    fn great_power_score(&self) -> f32 {
+        return self.great_power_score;
+    }
+
+    fn is_subject(&self) -> bool {
+        return self.overlord.is_some();
+    }
+}
+
+fn take_value(args: &mut Vec<String>, i: usize) -> Result<String, Box<dyn Error>> {
+    if i + 1 >= args.len() {
+        return Err(format!("Missing value for {}", args[i]).into());
+    }
+    return Ok(args.remove(i + 1));
+}
+
+impl Selection {
+    // Removes the selection flags from `args`, leaving the positional arguments of the mode
+    pub fn from_args(args: &mut Vec<String>) -> Result<Selection, Box<dyn Error>> {
+        let mut selection = Selection::default();
+        let mut i = 0;
+        while i < args.len() {
+            match args[i].as_str() {
+                "--players" => selection.players = true,
+                "--great-powers" => selection.great_powers = true,
+                "--exclude-subjects" => selection.exclude_subjects = true,
+                "--tags" => {
+                    let tags = take_value(args, i)?;
+                    selection.tags.extend(tags.split(',').map(|t| t.trim().to_uppercase()).filter(|t| !t.is_empty()));
+                }
+                "--top" => selection.top = Some(take_value(args, i)?.parse()?),
+                "--top-by" => {
+                    selection.top_by = match take_value(args, i)?.as_str() {
+                        "development" | "dev" => RankBy::Development,
+                        "gp" | "gp_score" => RankBy::GreatPowerScore,
+                        other => return Err(format!("Unknown ranking {:?}, expected development or gp", other).into()),
+                    }
+                }
+                "--min-development" => selection.min_development = Some(take_value(args, i)?.parse()?),
+                _ => {
+                    i += 1;
+                    continue;
+                }
+            }
+            args.remove(i);
+        }
+        return Ok(selection);
+    }
+
+    fn has_selectors(&self) -> bool {
+        return self.players || !self.tags.is_empty() || self.great_powers || self.top.is_some();
+    }
+
+    // `great_powers` is the save's own list, see `Extras::great_powers`
+    pub fn select<C>(&self, countries: &[(CountryTag, &C)], players: &HashMap<CountryTag, String>,
+        great_powers: &HashSet<CountryTag>) -> HashSet<CountryTag>
+    where C: SelectionInput {
+        let candidates: Vec<&(CountryTag, &C)> = countries
+                        .iter()
+                        .filter(|(_tag, c)| c.development() > 0.0)
+                        .filter(|(_tag, c)| self.min_development.is_none_or(|min| c.development() >= min))
+                        .filter(|(_tag, c)| !self.exclude_subjects || !c.is_subject())
+                        .collect();
+        if !self.has_selectors() {
+            return candidates.iter().map(|(tag, _c)| *tag).collect();
+        }
+
+        let mut selected = HashSet::new();
+        if self.players {
+            selected.extend(candidates.iter().filter(|(tag, _c)| players.contains_key(tag)).map(|(tag, _c)| *tag));
+        }
+        selected.extend(candidates.iter().filter(|(tag, _c)| self.tags.contains(&tag.to_string())).map(|(tag, _c)| *tag));
+
+        if self.great_powers {
+            selected.extend(candidates.iter().filter(|(tag, _c)| great_powers.contains(tag)).map(|(tag, _c)| *tag));
+        }
+
+        if let Some(top) = self.top {
+            let mut ranked = candidates.clone();
+            match self.top_by {
+                RankBy::Development => ranked.sort_by(|(_a, a), (_b, b)| b.development().total_cmp(&a.development())),
+                RankBy::GreatPowerScore => ranked.sort_by(|(_a, a), (_b, b)| b.great_power_score().total_cmp(&a.great_power_score())),
+            }
+            selected.extend(ranked.iter().take(top).map(|(tag, _c)| *tag));
+        }
+        return selected;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct TestCountry {
+        development: f32,
+        great_power_score: f32,
+        subject: bool,
+    }
+
+    impl SelectionInput for TestCountry {
+        fn development(&self) -> f32 {
+            return self.development;
+        }
+
+        fn great_power_score(&self) -> f32 {
+            return self.great_power_score;
+        }
+
+        fn is_subject(&self) -> bool {
+            return self.subject;
+        }
+    }
+
+    fn tag(tag: &str) -> CountryTag {
+        return tag.parse().unwrap();
+    }
+
+    // A01 is played and a great power, B01 its subject is played too, C01 has the highest great
+    // power score without being one, D01 has exactly 50 development and E01 is a dead tag
+    fn countries() -> Vec<(CountryTag, TestCountry)> {
+        let country = |development: f32, great_power_score: f32, subject: bool| TestCountry { development, great_power_score, subject };
+        return vec![
+            (tag("A01"), country(200.0, 300.0, false)),
+            (tag("B01"), country(80.0, 50.0, true)),
+            (tag("C01"), country(150.0, 400.0, false)),
+            (tag("D01"), country(50.0, 20.0, false)),
+            (tag("E01"), country(0.0, 0.0, false)),
+        ];
+    }
+
+    fn args(args: &str) -> Vec<String> {
+        return args.split_whitespace().map(|a| a.to_string()).collect();
+    }
+
+    fn select(flags: &str) -> Vec<String> {
+        let countries = countries();
+        let countries: Vec<(CountryTag, &TestCountry)> = countries.iter().map(|(tag, c)| (*tag, c)).collect();
+        let players = HashMap::from([(tag("A01"), "Alice".to_string()), (tag("B01"), "Bob".to_string())]);
+        let great_powers = HashSet::from([tag("A01")]);
+        let selection = Selection::from_args(&mut args(flags)).unwrap();
+        let mut selected: Vec<String> = selection.select(&countries, &players, &great_powers).iter().map(|t| t.to_string()).collect();
+        selected.sort();
+        return selected;
+    }
+
+    #[test]
+    fn selects() {
+        let cases: [(&str, &[&str]); 19] = [
+            ("", &["A01", "B01", "C01", "D01"]),
+            ("--exclude-subjects", &["A01", "C01", "D01"]),
+            // Exactly the minimum is enough
+            ("--min-development 50", &["A01", "B01", "C01", "D01"]),
+            ("--min-development 50.5", &["A01", "B01", "C01"]),
+            ("--players", &["A01", "B01"]),
+            ("--players --exclude-subjects", &["A01"]),
+            ("--players --min-development 100", &["A01"]),
+            ("--tags b01,D01", &["B01", "D01"]),
+            ("--tags B01,D01 --exclude-subjects", &["D01"]),
+            ("--tags B01,D01,E01 --min-development 50", &["B01", "D01"]),
+            // The save's great powers, not the highest scores
+            ("--great-powers", &["A01"]),
+            ("--great-powers --min-development 250", &[]),
+            ("--top 2", &["A01", "C01"]),
+            ("--top 2 --top-by gp", &["A01", "C01"]),
+            ("--top 3 --top-by gp --exclude-subjects", &["A01", "C01", "D01"]),
+            // The top is taken among the countries left after filtering
+            ("--top 1 --min-development 160", &["A01"]),
+            ("--top 10", &["A01", "B01", "C01", "D01"]),
+            ("--players --top 1 --top-by gp", &["A01", "B01", "C01"]),
+            ("--great-powers --tags D01 --exclude-subjects --min-development 50", &["A01", "D01"]),
+        ];
+        for (flags, expected) in cases {
+            assert_eq!(select(flags), expected, "{:?}", flags);
+        }
+    }
+
+    #[test]
+    fn from_args() {
+        let mut remaining = args("map saves --top 5 --top-by dev out.png --players --min-development 12.5");
+        let selection = Selection::from_args(&mut remaining).unwrap();
+        assert_eq!(remaining, args("map saves out.png"));
+        assert_eq!(selection.top, Some(5));
+        assert_eq!(selection.top_by, RankBy::Development);
+        assert!(selection.players);
+        assert_eq!(selection.min_development, Some(12.5));
+
+        let errors = [
+            ("--top", "Missing value for --top"),
+            ("--min-development", "Missing value for --min-development"),
+            ("--top-by size", "Unknown ranking \"size\", expected development or gp"),
+        ];
+        for (flags, expected) in errors {
+            let error = Selection::from_args(&mut args(flags)).unwrap_err();
+            assert_eq!(error.to_string(), expected, "{:?}", flags);
+        }
+        assert!(Selection::from_args(&mut args("--top many")).is_err());
+    }
+}
@@ -0,0 +1,166 @@
+use log::{error, info, trace};
+use std::error::Error;
+use std::io::Read;
+use std::result::Result;
+
+use serde::Serialize;
+use tiny_http::{Header, Method, Request, Response, Server};
+
+use crate::models::{CountryDelta, Eu4Stats, SaveSummary};
+
+const DEFAULT_SAVE_NAME: &str = "upload.eu4";
+// Uncompressed late game saves are well below this
+const MAX_UPLOAD_SIZE: usize = 512 * 1024 * 1024;
+
+#[derive(Default)]
+struct ServerState {
+    history: Vec<SaveSummary>,
+    latest: Option<Eu4Stats>,
+    previous: Option<Eu4Stats>,
+}
+
+impl ServerState {
+    fn push(&mut self, stats: Eu4Stats) {
+        self.history.push(SaveSummary {
+            id: self.history.len(),
+            file_name: stats.meta.file_name.clone(),
+            date: stats.meta.date.clone(),
+            parsed_at: stats.meta.parsed_at,
+            number_countries: stats.countries.len(),
+        });
+        self.previous = self.latest.replace(stats);
+    }
+}
+
+fn diff_stats(stats: &Eu4Stats, old_stats: Option<&Eu4Stats>) -> Vec<CountryDelta> {
+    return stats.countries.iter().map(|c| CountryDelta {
+        tag: c.tag.clone(),
+        name: c.name.clone(),
+        player: c.player.clone(),
+        country: c.country.clone(),
+        old_country: old_stats.and_then(|s| s.find_previous(c)).map(|o| o.country.clone()),
+    }).collect();
+}
+
+fn header(field: &str, value: &str) -> Header {
+    return Header::from_bytes(field.as_bytes(), value.as_bytes()).unwrap();
+}
+
+fn get_header<'a>(request: &'a Request, field: &str) -> Option<&'a str> {
+    return request.headers().iter().find(|h| h.field.as_str().as_str().eq_ignore_ascii_case(field)).map(|h| h.value.as_str());
+}
+
+fn json_response<T: Serialize>(value: &T) -> Response<std::io::Cursor<Vec<u8>>> {
+    match serde_json::to_vec(value) {
+        Ok(body) => Response::from_data(body).with_header(header("Content-Type", "application/json")),
+        Err(e) => error_response(500, &e.to_string()),
+    }
+}
+
+fn error_response(status: u16, message: &str) -> Response<std::io::Cursor<Vec<u8>>> {
+    return Response::from_string(message).with_status_code(status);
+}
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    return haystack.windows(needle.len()).position(|w| w == needle);
+}
+
+// Returns the file name and contents of the first part of a multipart/form-data body
+fn parse_multipart<'a>(body: &'a [u8], content_type: &str) -> Option<(Option<String>, &'a [u8])> {
+    let boundary = content_type.split(';').find_map(|p| p.trim().strip_prefix("boundary="))?.trim_matches('"');
+    let delimiter = format!("--{}", boundary);
+    let part_start = find(body, delimiter.as_bytes())? + delimiter.len();
+    let part = &body[part_start..];
+    let headers_end = find(part, b"\r\n\r\n")?;
+    let headers = String::from_utf8_lossy(&part[..headers_end]);
+    let file_name = headers.split(';')
+                    .find_map(|p| p.trim().strip_prefix("filename="))
+                    .map(|n| n.lines().next().unwrap_or(n).trim_matches('"').to_string());
+    let content = &part[headers_end + 4..];
+    let content_end = find(content, format!("\r\n{}", delimiter).as_bytes())?;
+    return Some((file_name, &content[..content_end]));
+}
+
+fn handle_upload<F>(request: &mut Request, state: &mut ServerState, generate: &mut F) -> Response<std::io::Cursor<Vec<u8>>>
+where F: FnMut(&str, &[u8]) -> Result<Eu4Stats, Box<dyn Error>> {
+    if request.body_length().is_some_and(|length| length > MAX_UPLOAD_SIZE) {
+        return error_response(413, &format!("Saves are limited to {} bytes", MAX_UPLOAD_SIZE));
+    }
+    // A chunked body has no length up front, so the limit is enforced while reading as well
+    let mut body = Vec::new();
+    if let Err(e) = request.as_reader().take(MAX_UPLOAD_SIZE as u64 + 1).read_to_end(&mut body) {
+        return error_response(400, &e.to_string());
+    }
+    if body.len() > MAX_UPLOAD_SIZE {
+        return error_response(413, &format!("Saves are limited to {} bytes", MAX_UPLOAD_SIZE));
+    }
+    trace!("Bytes received: {:?}", body.len());
+
+    let content_type = get_header(request, "Content-Type").unwrap_or("").to_string();
+    let (file_name, data) = if content_type.starts_with("multipart/form-data") {
+        let Some((file_name, data)) = parse_multipart(&body, &content_type) else {
+            return error_response(400, "Malformed multipart body");
+        };
+        (file_name, data)
+    } else {
+        (get_header(request, "X-Save-Name").map(|n| n.to_string()), body.as_slice())
+    };
+    let file_name = file_name.unwrap_or(DEFAULT_SAVE_NAME.to_string());
+
+    info!("Generating stats for uploaded save {:?}", file_name);
+    match generate(&file_name, data) {
+        Ok(stats) => {
+            let response = json_response(&stats);
+            state.push(stats);
+            response
+        }
+        Err(e) => {
+            error!("Error: {:?}", e);
+            error_response(422, &e.to_string())
+        }
+    }
+}
+
+fn handle_request<F>(request: &mut Request, state: &mut ServerState, generate: &mut F) -> Response<std::io::Cursor<Vec<u8>>>
+where F: FnMut(&str, &[u8]) -> Result<Eu4Stats, Box<dyn Error>> {
+    let path = request.url().split('?').next().unwrap_or("").trim_end_matches('/').to_string();
+    let segments: Vec<&str> = path.split('/').skip(1).collect();
+    let method = request.method().clone();
+    match (method, segments.as_slice()) {
+        (Method::Options, _) => Response::from_data(Vec::new()).with_status_code(204),
+        (Method::Post, ["upload"]) => handle_upload(request, state, generate),
+        (Method::Get, ["stats"]) => match &state.latest {
+            Some(stats) => json_response(stats),
+            None => error_response(404, "No save has been parsed yet"),
+        },
+        (Method::Get, ["stats", tag]) => match state.latest.as_ref().and_then(|s| s.countries.iter().find(|c| c.tag == *tag)) {
+            Some(country) => json_response(country),
+            None => error_response(404, &format!("No stats for {}", tag)),
+        },
+        (Method::Get, ["diff"]) => match &state.latest {
+            Some(stats) => json_response(&diff_stats(stats, state.previous.as_ref())),
+            None => error_response(404, "No save has been parsed yet"),
+        },
+        (Method::Get, ["history"]) => json_response(&state.history),
+        _ => error_response(404, "Not found"),
+    }
+}
+
+// Serves the stats of uploaded saves. Only the latest two saves are kept, which is all `/diff` needs.
+pub fn serve<F>(address: &str, mut generate: F) -> Result<(), Box<dyn Error>>
+where F: FnMut(&str, &[u8]) -> Result<Eu4Stats, Box<dyn Error>> {
+    let server = Server::http(address).map_err(|e| -> Box<dyn Error> { e })?;
+    let mut state = ServerState::default();
+    info!("Listening on {}", address);
+
+    for mut request in server.incoming_requests() {
+        trace!("{} {}", request.method(), request.url());
+        let response = handle_request(&mut request, &mut state, &mut generate)
+                        .with_header(header("Access-Control-Allow-Origin", "*"))
+                        .with_header(header("Access-Control-Allow-Headers", "Content-Type, X-Save-Name"));
+        if let Err(e) = request.respond(response) {
+            error!("Error: {:?}", e);
+        }
+    }
+    return Ok(());
+}
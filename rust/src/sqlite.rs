@@ -0,0 +1,279 @@
+use log::info;
+use std::error::Error;
+use std::path::Path;
+use std::result::Result;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use rusqlite::{params, Connection, Transaction};
+
+use crate::models::{CountryStats, Eu4Stats};
+
+const SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS saves (
+        id INTEGER PRIMARY KEY,
+        file_name TEXT NOT NULL,
+        date TEXT NOT NULL,
+        exported_at INTEGER NOT NULL,
+        UNIQUE (file_name, date)
+    );
+    CREATE TABLE IF NOT EXISTS countries (
+        save_id INTEGER NOT NULL REFERENCES saves (id) ON DELETE CASCADE,
+        date TEXT NOT NULL,
+        tag TEXT NOT NULL,
+        name TEXT NOT NULL,
+        player TEXT,
+        total_development REAL NOT NULL,
+        real_development REAL NOT NULL,
+        gp_score INTEGER NOT NULL,
+        total_ideas INTEGER NOT NULL,
+        current_manpower INTEGER NOT NULL,
+        max_manpower INTEGER NOT NULL,
+        adm_tech INTEGER NOT NULL,
+        dip_tech INTEGER NOT NULL,
+        mil_tech INTEGER NOT NULL,
+        average_monarch_adm REAL NOT NULL,
+        average_monarch_dip REAL NOT NULL,
+        average_monarch_mil REAL NOT NULL,
+        income REAL NOT NULL,
+        number_provinces INTEGER NOT NULL,
+        number_buildings INTEGER NOT NULL,
+        buildings_value INTEGER NOT NULL,
+        buildings_per_province REAL NOT NULL,
+        innovativeness REAL NOT NULL,
+        absolutism REAL NOT NULL,
+        average_development REAL NOT NULL,
+        average_development_real REAL NOT NULL,
+        PRIMARY KEY (save_id, tag)
+    );
+    CREATE TABLE IF NOT EXISTS military (
+        save_id INTEGER NOT NULL REFERENCES saves (id) ON DELETE CASCADE,
+        date TEXT NOT NULL,
+        tag TEXT NOT NULL,
+        army_tradition REAL NOT NULL,
+        army_morale REAL NOT NULL,
+        army_discipline REAL NOT NULL,
+        army_force_limit INTEGER NOT NULL,
+        army_professionalism REAL NOT NULL,
+        siege_ability REAL NOT NULL,
+        fort_defense REAL NOT NULL,
+        infantry_ability REAL NOT NULL,
+        cavalry_ability REAL NOT NULL,
+        artillery_ability REAL NOT NULL,
+        fire_dealt REAL NOT NULL,
+        fire_received REAL NOT NULL,
+        shock_dealt REAL NOT NULL,
+        shock_received REAL NOT NULL,
+        leader_fire INTEGER NOT NULL,
+        leader_shock INTEGER NOT NULL,
+        leader_maneuver INTEGER NOT NULL,
+        leader_siege INTEGER NOT NULL,
+        mercenary_discipline REAL NOT NULL,
+        naval_tradition REAL NOT NULL,
+        naval_morale REAL NOT NULL,
+        naval_force_limit INTEGER NOT NULL,
+        PRIMARY KEY (save_id, tag)
+    );
+    CREATE TABLE IF NOT EXISTS mana (
+        save_id INTEGER NOT NULL REFERENCES saves (id) ON DELETE CASCADE,
+        date TEXT NOT NULL,
+        tag TEXT NOT NULL,
+        earned_adm INTEGER NOT NULL,
+        earned_dip INTEGER NOT NULL,
+        earned_mil INTEGER NOT NULL,
+        spent_adm INTEGER NOT NULL,
+        spent_dip INTEGER NOT NULL,
+        spent_mil INTEGER NOT NULL,
+        spent_developing_adm INTEGER NOT NULL,
+        spent_developing_dip INTEGER NOT NULL,
+        spent_developing_mil INTEGER NOT NULL,
+        developing_ratio TEXT NOT NULL,
+        spent_tech INTEGER NOT NULL,
+        spent_culture INTEGER NOT NULL,
+        spent_coring INTEGER NOT NULL,
+        spent_inflation INTEGER NOT NULL,
+        spent_ideas INTEGER NOT NULL,
+        spent_force_march INTEGER NOT NULL,
+        spent_generals INTEGER NOT NULL,
+        spent_unjustified INTEGER NOT NULL,
+        PRIMARY KEY (save_id, tag)
+    );
+    CREATE TABLE IF NOT EXISTS ideas (
+        save_id INTEGER NOT NULL REFERENCES saves (id) ON DELETE CASCADE,
+        date TEXT NOT NULL,
+        tag TEXT NOT NULL,
+        idea_group TEXT NOT NULL,
+        level INTEGER NOT NULL,
+        PRIMARY KEY (save_id, tag, idea_group)
+    );
+    CREATE TABLE IF NOT EXISTS income_history (
+        save_id INTEGER NOT NULL REFERENCES saves (id) ON DELETE CASCADE,
+        date TEXT NOT NULL,
+        tag TEXT NOT NULL,
+        year INTEGER NOT NULL,
+        income INTEGER NOT NULL,
+        PRIMARY KEY (save_id, tag, year)
+    );
+";
+
+fn insert_country(tx: &Transaction, save_id: i64, date: &str, c: &CountryStats) -> Result<(), Box<dyn Error>> {
+    let country = &c.country;
+    tx.prepare_cached("INSERT INTO countries VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17, ?18, ?19, ?20, ?21, ?22, ?23, ?24, ?25, ?26)")?
+        .execute(params![
+            save_id, date, c.tag, c.name, c.player,
+            country.total_development, country.real_development, country.gp_score,
+            country.total_ideas, country.current_manpower, country.max_manpower,
+            country.technology[0], country.technology[1], country.technology[2],
+            country.average_monarch[0], country.average_monarch[1], country.average_monarch[2],
+            country.income, country.number_provinces, country.number_buildings,
+            country.buildings_value, country.buildings_per_province,
+            country.innovativeness, country.absolutism,
+            country.average_development, country.average_development_real,
+        ])?;
+
+    let military = &c.military;
+    tx.prepare_cached("INSERT INTO military VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17, ?18, ?19, ?20, ?21, ?22, ?23, ?24, ?25)")?
+        .execute(params![
+            save_id, date, c.tag,
+            military.army_tradition, military.army_morale, military.army_discipline,
+            military.army_force_limit, military.army_professionalism,
+            military.siege_ability, military.fort_defense,
+            military.infantry_ability, military.cavalry_ability, military.artillery_ability,
+            military.fire_dealt, military.fire_received, military.shock_dealt, military.shock_received,
+            military.leader_fire, military.leader_shock, military.leader_maneuver, military.leader_siege,
+            military.mercenary_discipline,
+            military.naval_tradition, military.naval_morale, military.naval_force_limit,
+        ])?;
+
+    let mana = &c.mana;
+    tx.prepare_cached("INSERT INTO mana VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17, ?18, ?19, ?20, ?21)")?
+        .execute(params![
+            save_id, date, c.tag,
+            country.powers_earned[0], country.powers_earned[1], country.powers_earned[2],
+            mana.mana_spent[0], mana.mana_spent[1], mana.mana_spent[2],
+            mana.spent_developing[0], mana.spent_developing[1], mana.spent_developing[2],
+            mana.developing_ratio, mana.spent_tech, mana.spent_culture, mana.spent_coring,
+            mana.spent_inflation, mana.spent_ideas, mana.spent_force_march,
+            mana.spent_generals, mana.spent_unjustified,
+        ])?;
+
+    let mut insert_idea = tx.prepare_cached("INSERT INTO ideas VALUES (?1, ?2, ?3, ?4, ?5)")?;
+    for (idea_group, level) in &country.ideas {
+        insert_idea.execute(params![save_id, date, c.tag, idea_group, level])?;
+    }
+
+    let mut insert_income = tx.prepare_cached("INSERT INTO income_history VALUES (?1, ?2, ?3, ?4, ?5)")?;
+    for (year, income) in country.income_history.iter() {
+        insert_income.execute(params![save_id, date, c.tag, year, income])?;
+    }
+    return Ok(());
+}
+
+// Adds the stats of a save to the database. Exporting the same save again replaces its rows, so
+// rerunning the export over a whole campaign folder is safe.
+pub fn export_stats<P>(db_path: P, stats: &Eu4Stats) -> Result<i64, Box<dyn Error>>
+where P: AsRef<Path> {
+    let mut conn = Connection::open(&db_path)?;
+    conn.execute_batch("PRAGMA foreign_keys = ON;")?;
+    conn.execute_batch(SCHEMA)?;
+
+    let file_name = stats.meta.file_name.as_str();
+    let date = stats.meta.date.as_str();
+    let tx = conn.transaction()?;
+    tx.execute("DELETE FROM saves WHERE file_name = ?1 AND date = ?2", params![file_name, date])?;
+    let exported_at = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs() as i64).unwrap_or(0);
+    tx.execute(
+        "INSERT INTO saves (file_name, date, exported_at) VALUES (?1, ?2, ?3)",
+        params![file_name, date, exported_at],
+    )?;
+    let save_id = tx.last_insert_rowid();
+    for country in &stats.countries {
+        insert_country(&tx, save_id, date, country)?;
+    }
+    tx.commit()?;
+
+    info!("Exported {} countries as save {} to {:?}", stats.countries.len(), save_id, db_path.as_ref());
+    return Ok(save_id);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use std::path::PathBuf;
+
+    use crate::models::{CondensedCountry, SaveMeta};
+
+    fn save(file_name: &str, date: &str, tags: &[&str]) -> Eu4Stats {
+        let countries = tags.iter().map(|tag| CountryStats {
+            tag: tag.to_string(),
+            name: tag.to_string(),
+            country: CondensedCountry {
+                ideas: vec![("innovative_ideas".to_string(), 3), ("trade_ideas".to_string(), 7)],
+                income_history: vec![(1445, 8), (1448, 10)].into_boxed_slice(),
+                ..Default::default()
+            },
+            ..Default::default()
+        }).collect();
+        return Eu4Stats {
+            meta: SaveMeta { file_name: file_name.to_string(), date: date.to_string(), ..Default::default() },
+            countries,
+            ..Default::default()
+        };
+    }
+
+    // A fresh database file for each test
+    fn db_path(name: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("eu4_stats_{}_{}.sqlite", name, std::process::id()));
+        let _ = fs::remove_file(&path);
+        return path;
+    }
+
+    fn count(conn: &Connection, table: &str, save_id: i64) -> i64 {
+        let query = if table == "saves" {
+            "SELECT COUNT(*) FROM saves WHERE id = ?1".to_string()
+        } else {
+            format!("SELECT COUNT(*) FROM {} WHERE save_id = ?1", table)
+        };
+        return conn.query_row(&query, params![save_id], |row| row.get(0)).unwrap();
+    }
+
+    fn count_all(conn: &Connection, table: &str) -> i64 {
+        return conn.query_row(&format!("SELECT COUNT(*) FROM {}", table), [], |row| row.get(0)).unwrap();
+    }
+
+    const TABLES: [(&str, i64); 6] = [("saves", 1), ("countries", 2), ("military", 2), ("mana", 2), ("ideas", 4), ("income_history", 4)];
+
+    #[test]
+    fn same_save_is_replaced() {
+        let path = db_path("replaced");
+        let stats = save("campaign.eu4", "1450.1.1", &["A01", "B01"]);
+        export_stats(&path, &stats).unwrap();
+        let save_id = export_stats(&path, &stats).unwrap();
+
+        let conn = Connection::open(&path).unwrap();
+        for (table, expected) in TABLES {
+            assert_eq!(count_all(&conn, table), expected, "{}", table);
+            assert_eq!(count(&conn, table, save_id), expected, "{}", table);
+        }
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn saves_are_kept_apart() {
+        let path = db_path("apart");
+        let first = export_stats(&path, &save("campaign.eu4", "1450.1.1", &["A01", "B01"])).unwrap();
+        // Same file name, later in the campaign
+        let second = export_stats(&path, &save("campaign.eu4", "1460.1.1", &["A01", "B01"])).unwrap();
+        assert_ne!(first, second);
+
+        let conn = Connection::open(&path).unwrap();
+        for save_id in [first, second] {
+            for (table, expected) in TABLES {
+                assert_eq!(count(&conn, table, save_id), expected, "{} of save {}", table, save_id);
+            }
+        }
+        let date: String = conn.query_row("SELECT date FROM countries WHERE save_id = ?1 AND tag = 'A01'", params![second], |row| row.get(0)).unwrap();
+        assert_eq!(date, "1460.1.1");
+        fs::remove_file(&path).unwrap();
+    }
+}
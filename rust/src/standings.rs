@@ -0,0 +1,121 @@
+// Rank and percentile of every metric, among all selected countries and among the players, so the
+// frontend can say "3rd in income, 11th in discipline" without sorting each column. For metrics
+// where lower is better, such as battles lost, the lowest value ranks first.
+use std::collections::{BTreeMap, HashSet};
+
+use crate::models::{CountryStats, MetricDefinition, Standing};
+use crate::ranking::get_ranks;
+
+// The share of `values` other than `value` that are lower, 100 when there are no others
+fn get_percentile(values: &[f32], value: f32) -> f32 {
+    if values.len() <= 1 {
+        return 100.0;
+    }
+    let lower = values.iter().filter(|v| **v < value).count();
+    return (lower as f32 / (values.len() - 1) as f32 * 1000.0).round() / 10.0;
+}
+
+// For `metric`, the index into `countries`, the rank and the percentile of each country among
+// those `included`
+fn get_positions<F>(countries: &[CountryStats], metric: &str, lower_is_better: bool, included: F) -> Vec<(usize, usize, f32)>
+where F: Fn(&CountryStats) -> bool {
+    // Negated, the best value is the highest either way
+    let sign = if lower_is_better { -1.0 } else { 1.0 };
+    let entries: Vec<(usize, f32)> = countries.iter().enumerate()
+        .filter(|(_i, c)| included(c))
+        .filter_map(|(i, c)| c.metric_values.get(metric).map(|v| (i, *v * sign)))
+        .collect();
+    let values: Vec<f32> = entries.iter().map(|(_i, v)| *v).collect();
+    let ranks = get_ranks(&values);
+    return entries.iter().zip(ranks)
+        .map(|((i, value), rank)| (*i, rank, get_percentile(&values, *value)))
+        .collect();
+}
+
+pub fn generate_standings(countries: &mut [CountryStats], definitions: &[MetricDefinition]) {
+    let lower_is_better: HashSet<&str> = definitions.iter().filter(|d| d.lower_is_better).map(|d| d.key.as_str()).collect();
+    let mut metrics: Vec<String> = countries.iter().flat_map(|c| c.metric_values.keys().cloned()).collect();
+    metrics.sort();
+    metrics.dedup();
+
+    let mut standings: Vec<BTreeMap<String, Standing>> = vec![BTreeMap::new(); countries.len()];
+    for metric in &metrics {
+        let lower = lower_is_better.contains(metric.as_str());
+        for (i, rank, percentile) in get_positions(countries, metric, lower, |_c| true) {
+            standings[i].insert(metric.clone(), Standing { rank, percentile, ..Default::default() });
+        }
+        for (i, rank, percentile) in get_positions(countries, metric, lower, |c| c.player.is_some()) {
+            if let Some(standing) = standings[i].get_mut(metric) {
+                standing.player_rank = Some(rank);
+                standing.player_percentile = Some(percentile);
+            }
+        }
+    }
+    for (country, standings) in countries.iter_mut().zip(standings) {
+        country.standings = standings;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn country(player: Option<&str>, values: &[(&str, f32)]) -> CountryStats {
+        return CountryStats {
+            player: player.map(|p| p.to_string()),
+            metric_values: values.iter().map(|(key, value)| (key.to_string(), *value)).collect(),
+            ..Default::default()
+        };
+    }
+
+    fn get_standing(country: &CountryStats, metric: &str) -> (usize, f32, Option<usize>, Option<f32>) {
+        let standing = &country.standings[metric];
+        return (standing.rank, standing.percentile, standing.player_rank, standing.player_percentile);
+    }
+
+    #[test]
+    fn percentile() {
+        let cases = [
+            (vec![5.0], 5.0, 100.0),
+            (vec![1.0, 2.0, 3.0], 3.0, 100.0),
+            (vec![1.0, 2.0, 3.0], 2.0, 50.0),
+            (vec![1.0, 2.0, 3.0], 1.0, 0.0),
+            // Ties don't count as lower
+            (vec![2.0, 2.0, 1.0], 2.0, 50.0),
+            (vec![2.0, 2.0, 2.0], 2.0, 0.0),
+            (vec![1.0, 2.0, 3.0, 4.0], 2.0, 33.3),
+        ];
+        for (values, value, expected) in cases {
+            assert_eq!(get_percentile(&values, value), expected, "{} in {:?}", value, values);
+        }
+    }
+
+    #[test]
+    fn standings() {
+        let definitions = [MetricDefinition { key: "lost".to_string(), lower_is_better: true, ..Default::default() }];
+        let mut countries = vec![
+            country(Some("Alice"), &[("income", 10.0), ("lost", 3.0)]),
+            country(None, &[("income", 30.0), ("lost", 0.0)]),
+            country(Some("Bob"), &[("income", 20.0), ("lost", 3.0)]),
+            country(None, &[("income", 20.0)]),
+        ];
+        generate_standings(&mut countries, &definitions);
+
+        let cases = [
+            (0, "income", (4, 0.0, Some(2), Some(0.0))),
+            (1, "income", (1, 100.0, None, None)),
+            // Tied with the fourth country
+            (2, "income", (2, 33.3, Some(1), Some(100.0))),
+            (3, "income", (2, 33.3, None, None)),
+            // Fewer is better, and the third country shares the second place
+            (0, "lost", (2, 0.0, Some(1), Some(0.0))),
+            (1, "lost", (1, 100.0, None, None)),
+            (2, "lost", (2, 0.0, Some(1), Some(0.0))),
+        ];
+        for (i, metric, expected) in cases {
+            assert_eq!(get_standing(&countries[i], metric), expected, "{} {}", i, metric);
+        }
+        // Countries without a metric have no standing in it
+        assert!(!countries[3].standings.contains_key("lost"));
+    }
+}
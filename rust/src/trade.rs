@@ -0,0 +1,83 @@
+use std::collections::HashSet;
+use std::error::Error;
+use std::result::Result;
+
+use eu4save::CountryTag;
+
+use crate::extras::{Extras, TradeNode, TradeNodeCountry};
+use crate::models;
+
+// A merchant with `type=1` is steering trade downstream, otherwise it is collecting
+fn get_merchant(country: &TradeNodeCountry) -> Option<String> {
+    if !country.has_trader {
+        return None;
+    }
+    let merchant = if country.kind == 1 { "steer" } else { "collect" };
+    return Some(merchant.to_string());
+}
+
+fn get_power_share(node: &TradeNode, country: &TradeNodeCountry) -> f32 {
+    if node.total <= 0.0 {
+        return 0.0;
+    }
+    return country.val / node.total;
+}
+
+// Keeps every node, but only lists the `included` countries in it
+pub fn generate_trade(extras: &Extras, included: &HashSet<CountryTag>) -> Result<Vec<models::TradeNodeStats>, Box<dyn Error>> {
+    let mut nodes = Vec::new();
+    for node in &extras.trade.node {
+        let mut countries: Vec<models::TradeNodeCountry> = node.countries
+            .iter()
+            .filter(|(tag, c)| included.contains(tag) && c.val > 0.0)
+            .map(|(tag, c)| models::TradeNodeCountry {
+                tag: tag.to_string(),
+                trade_power: c.val,
+                power_share: get_power_share(node, c),
+                income: c.money,
+                merchant: get_merchant(c),
+                home_node: c.has_capital,
+            })
+            .collect();
+        countries.sort_by(|a, b| b.trade_power.total_cmp(&a.trade_power));
+        nodes.push(models::TradeNodeStats {
+            name: node.definitions.clone(),
+            value: node.current,
+            local_value: node.local_value,
+            total_power: node.total,
+            countries,
+        });
+    }
+    return Ok(nodes);
+}
+
+// The home node is the one holding the country's capital
+pub fn generate_country_trade(extras: &Extras, tag: &CountryTag) -> Result<models::CountryTrade, Box<dyn Error>> {
+    let mut trade = models::CountryTrade::default();
+    for node in &extras.trade.node {
+        let Some((_tag, country)) = node.countries.iter().find(|(t, _c)| t == tag) else { continue };
+        if country.has_capital {
+            trade.home_node = Some(node.definitions.clone());
+        }
+        let merchant = get_merchant(country);
+        match merchant.as_deref() {
+            Some("steer") => trade.merchants_steering += 1,
+            Some(_) => trade.merchants_collecting += 1,
+            None => {}
+        }
+        if country.val <= 0.0 && country.money <= 0.0 && merchant.is_none() {
+            continue;
+        }
+        trade.trade_power += country.val;
+        trade.trade_income += country.money;
+        trade.nodes.push(models::NodeTrade {
+            node: node.definitions.clone(),
+            trade_power: country.val,
+            power_share: get_power_share(node, country),
+            income: country.money,
+            merchant,
+        });
+    }
+    trade.nodes.sort_by(|a, b| b.income.total_cmp(&a.income));
+    return Ok(trade);
+}
@@ -0,0 +1,194 @@
+use std::collections::{HashMap, HashSet};
+use std::error::Error;
+use std::result::Result;
+
+use eu4save::{CountryTag, Eu4Date};
+use eu4save::models::{Battle, WarEvent, WarHistory};
+use eu4save::query::Query;
+
+use crate::extras::{ExtraCountry, ExtraWar, Extras};
+use crate::models;
+
+// `losses.members` holds battle, attrition and captured losses for each of infantry, cavalry,
+// artillery, heavy ships, light ships, galleys and transports, in that order
+pub fn get_unit_losses(members: &[u32]) -> models::UnitLosses {
+    let lost = |unit: usize| members.iter().skip(unit * 3).take(3).sum::<u32>() as i32;
+    return models::UnitLosses {
+        infantry: lost(0),
+        cavalry: lost(1),
+        artillery: lost(2),
+        heavy_ships: lost(3),
+        light_ships: lost(4),
+        galleys: lost(5),
+        transports: lost(6),
+    };
+}
+
+// Battles fought by a country as the leader of its side. Allies whose armies joined a battle
+// aren't recorded in the save, so they don't get the battle.
+#[derive(Debug, Default)]
+pub struct BattleRecord {
+    pub won: i32,
+    pub lost: i32,
+    pub largest: Option<models::LargestBattle>,
+}
+
+fn get_battle_size(battle: &Battle) -> i32 {
+    let mut size = 0;
+    for side in [&battle.attacker, &battle.defender] {
+        size += (side.infantry + side.cavalry + side.artillery) as i32;
+        size += (side.heavy_ship + side.light_ship + side.galley + side.transport) as i32;
+    }
+    return size;
+}
+
+pub fn get_battle_records(save_query: &Query) -> HashMap<CountryTag, BattleRecord> {
+    let game = &save_query.save().game;
+    let histories = game.active_wars.iter().map(|w| &w.history)
+                    .chain(game.previous_wars.iter().map(|w| &w.history));
+    let mut records: HashMap<CountryTag, BattleRecord> = HashMap::new();
+    for history in histories {
+        for (date, event) in &history.events {
+            let WarEvent::Battle(battle) = event else { continue };
+            let size = get_battle_size(battle);
+            for (side, won) in [(&battle.attacker, battle.result), (&battle.defender, !battle.result)] {
+                let record = records.entry(side.country).or_default();
+                if won {
+                    record.won += 1;
+                } else {
+                    record.lost += 1;
+                }
+                let is_largest = match &record.largest {
+                    Some(largest) => size > largest.size,
+                    None => true,
+                };
+                if is_largest {
+                    let report = get_battle(save_query, date, battle);
+                    record.largest = Some(models::LargestBattle {
+                        name: report.name,
+                        date: report.date,
+                        location: report.location,
+                        size,
+                    });
+                }
+            }
+        }
+    }
+    return records;
+}
+
+pub fn generate_casualties(country: Option<&ExtraCountry>, record: Option<&BattleRecord>) -> Result<models::CountryCasualties, Box<dyn Error>> {
+    let members = country.map(|c| c.losses.members.as_slice()).unwrap_or_default();
+    let lost = |i: usize| members.get(i).copied().unwrap_or(0) as i32;
+    let ships = get_unit_losses(members);
+    let casualties = models::CountryCasualties {
+        infantry_combat: lost(0),
+        infantry_attrition: lost(1),
+        cavalry_combat: lost(3),
+        cavalry_attrition: lost(4),
+        artillery_combat: lost(6),
+        artillery_attrition: lost(7),
+        total_land_losses: lost(0) + lost(1) + lost(3) + lost(4) + lost(6) + lost(7),
+        ships_lost: ships.heavy_ships + ships.light_ships + ships.galleys + ships.transports,
+        battles_won: record.map(|r| r.won).unwrap_or(0),
+        battles_lost: record.map(|r| r.lost).unwrap_or(0),
+        largest_battle: record.and_then(|r| r.largest.clone()),
+    };
+    return Ok(casualties);
+}
+
+fn get_battle_side(side: &eu4save::models::BattleSide) -> models::BattleSide {
+    return models::BattleSide {
+        country: side.country.to_string(),
+        commander: side.commander.clone(),
+        infantry: side.infantry as i32,
+        cavalry: side.cavalry as i32,
+        artillery: side.artillery as i32,
+        ships: (side.heavy_ship + side.light_ship + side.galley + side.transport) as i32,
+        losses: side.losses as i32,
+    };
+}
+
+fn get_battle(save_query: &Query, date: &Eu4Date, battle: &Battle) -> models::BattleReport {
+    let location = save_query.save().game.provinces
+                    .get(&battle.location)
+                    .map(|p| p.name.clone())
+                    .unwrap_or_else(|| battle.location.to_string());
+    return models::BattleReport {
+        name: battle.name.clone(),
+        date: date.iso_8601().to_string(),
+        location,
+        attacker_won: battle.result,
+        attacker: get_battle_side(&battle.attacker),
+        defender: get_battle_side(&battle.defender),
+    };
+}
+
+fn get_sides(history: &WarHistory) -> Vec<(CountryTag, &'static str)> {
+    let mut sides = Vec::new();
+    for (_date, event) in &history.events {
+        match event {
+            WarEvent::AddAttacker(tag) if !sides.iter().any(|(t, _)| t == tag) => sides.push((*tag, "attacker")),
+            WarEvent::AddDefender(tag) if !sides.iter().any(|(t, _)| t == tag) => sides.push((*tag, "defender")),
+            _ => {}
+        }
+    }
+    return sides;
+}
+
+fn generate_war(
+    save_query: &Query,
+    name: &str,
+    active: bool,
+    history: &WarHistory,
+    participants: &[eu4save::models::WarParticipant],
+    leaders: (&CountryTag, &CountryTag),
+    extra: Option<&ExtraWar>) -> models::WarReport {
+
+    let sides = get_sides(history);
+    let dates: Vec<&Eu4Date> = history.events.iter().map(|(date, _)| date).collect();
+    let war_goal = extra.and_then(|e| e.war_goal.as_ref());
+    return models::WarReport {
+        name: name.to_string(),
+        active,
+        start_date: dates.first().map(|d| d.iso_8601().to_string()),
+        end_date: if active { None } else { dates.last().map(|d| d.iso_8601().to_string()) },
+        casus_belli: war_goal.and_then(|g| g.casus_belli.clone().or(g.kind.clone())),
+        war_score: extra.and_then(|e| e.attacker_score),
+        attacker_leader: leaders.0.to_string(),
+        defender_leader: leaders.1.to_string(),
+        participants: participants.iter().map(|p| models::WarParticipant {
+            tag: p.tag.to_string(),
+            side: sides.iter().find(|(t, _)| *t == p.tag).map(|(_, s)| s.to_string()).unwrap_or_default(),
+            participation: p.value,
+            losses: get_unit_losses(&p.losses.members),
+        }).collect(),
+        battles: history.events.iter().filter_map(|(date, event)| match event {
+            WarEvent::Battle(battle) => Some(get_battle(save_query, date, battle)),
+            _ => None,
+        }).collect(),
+    };
+}
+
+// Keeps the wars that at least one of the `included` countries took part in
+pub fn generate_wars(save_query: &Query, extras: &Extras, included: &HashSet<CountryTag>) -> Result<Vec<models::WarReport>, Box<dyn Error>> {
+    let game = &save_query.save().game;
+    let mut wars = Vec::new();
+    // The extras come from the same save in the same order. War names repeat, so they are paired by
+    // position, with the name only as a check.
+    for (i, war) in game.active_wars.iter().enumerate() {
+        if war.participants.iter().any(|p| included.contains(&p.tag)) {
+            let extra = extras.active_war.get(i).filter(|e| e.name == war.name);
+            wars.push(generate_war(save_query, &war.name, true, &war.history, &war.participants,
+                                   (&war.original_attacker, &war.original_defender), extra));
+        }
+    }
+    for (i, war) in game.previous_wars.iter().enumerate() {
+        if war.participants.iter().any(|p| included.contains(&p.tag)) {
+            let extra = extras.previous_war.get(i).filter(|e| e.name == war.name);
+            wars.push(generate_war(save_query, &war.name, false, &war.history, &war.participants,
+                                   (&war.original_attacker, &war.original_defender), extra));
+        }
+    }
+    return Ok(wars);
+}
@@ -0,0 +1,77 @@
+use log::{error, info, trace, warn};
+use std::collections::HashMap;
+use std::error::Error;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::result::Result;
+use std::thread;
+use std::time::{Duration, SystemTime};
+
+use crate::models::Eu4Stats;
+use crate::write_stats;
+
+const POLL_INTERVAL: Duration = Duration::from_secs(2);
+const STATS_FILE: &str = "parsed_country.json";
+const OLD_STATS_FILE: &str = "old_parsed_country.json";
+
+fn scan_saves(save_dir: &Path) -> Result<HashMap<PathBuf, SystemTime>, Box<dyn Error>> {
+    let mut saves = HashMap::new();
+    for entry in fs::read_dir(save_dir)? {
+        let path = entry?.path();
+        if path.extension().is_some_and(|e| e == "eu4") {
+            // The file can disappear between listing and stat, e.g. when the game replaces an autosave
+            let Ok(metadata) = fs::metadata(&path) else { continue };
+            saves.insert(path, metadata.modified()?);
+        }
+    }
+    return Ok(saves);
+}
+
+fn rotate_and_write(stats: &Eu4Stats, output_dir: &Path) -> Result<(), Box<dyn Error>> {
+    let json_path = output_dir.join(STATS_FILE);
+    if json_path.exists() {
+        fs::rename(&json_path, output_dir.join(OLD_STATS_FILE))?;
+    }
+    return write_stats(stats, json_path);
+}
+
+// Saves that already exist when watching starts are ignored. A new or updated save is only parsed
+// once its modification time has been stable for a full poll, so half-written files are skipped.
+pub fn watch_saves<P, Q, F>(save_dir: P, output_dir: Q, mut generate: F) -> Result<(), Box<dyn Error>>
+where P: AsRef<Path>, Q: AsRef<Path>, F: FnMut(&Path) -> Result<Eu4Stats, Box<dyn Error>> {
+    let save_dir = save_dir.as_ref();
+    let output_dir = output_dir.as_ref();
+    let mut processed = scan_saves(save_dir)?;
+    let mut pending: HashMap<PathBuf, SystemTime> = HashMap::new();
+    info!("Watching {:?} for saves, writing stats to {:?}", save_dir, output_dir);
+
+    loop {
+        thread::sleep(POLL_INTERVAL);
+        // The directory can be briefly unavailable, e.g. while a cloud sync replaces it
+        let saves = match scan_saves(save_dir) {
+            Ok(saves) => saves,
+            Err(e) => {
+                warn!("Could not scan {:?}: {:?}", save_dir, e);
+                continue;
+            }
+        };
+        for (path, modified) in saves {
+            if processed.get(&path) == Some(&modified) {
+                continue;
+            }
+            if pending.get(&path) != Some(&modified) {
+                trace!("Save changed: {:?}", path);
+                pending.insert(path, modified);
+                continue;
+            }
+
+            pending.remove(&path);
+            processed.insert(path.clone(), modified);
+            info!("Regenerating stats for {:?}", path);
+            match generate(&path).and_then(|stats| rotate_and_write(&stats, output_dir)) {
+                Ok(()) => info!("Finished regenerating stats for {:?}", path),
+                Err(e) => error!("Error: {:?}", e),
+            }
+        }
+    }
+}
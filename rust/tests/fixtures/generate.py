@@ -0,0 +1,429 @@
+# Writes the fixture saves used by tests/golden.rs. The saves are cut down to the parts the parser
+# reads, so they stay small enough to commit and to read in a diff. After changing them, rerun
+#
+#     python3 generate.py
+#     UPDATE_GOLDEN=1 cargo test --test golden
+#
+# and check the changes to the golden files before committing them.
+import os
+import struct
+from zipfile import ZipFile, ZipInfo, ZIP_DEFLATED
+
+HERE = os.path.dirname(os.path.abspath(__file__))
+HEADER = 'EU4txt\n'
+# Text saves are written by the game in Windows-1252
+ENCODING = 'cp1252'
+
+
+def meta(date, player, mods=(), multiplayer=False):
+    mods_names = ''.join(
+        '\t{\n\t\tfilename="mod/%s.mod"\n\t\tname="%s"\n\t}\n' % (m.lower(), m) for m in mods
+    )
+    multi_player = 'yes' if multiplayer else 'no'
+    return f'''date={date}
+save_game="{player}.eu4"
+player="{player}"
+displayed_country_name="{player}"
+savegame_version={{
+\tfirst=1
+\tsecond=35
+\tthird=6
+\tforth=0
+\tname="Stranger"
+}}
+savegame_versions={{
+\t"1.35.6.0"
+}}
+dlc_enabled={{
+\t"Conquest of Paradise"
+\t"Art of War"
+}}
+mods_enabled_names={{
+{mods_names}}}
+multi_player={multi_player}
+not_observer=no
+campaign_id="00000000-0000-0000-0000-000000000000"
+campaign_length=0
+is_random_new_world=no
+checksum="0123456789abcdef0123456789abcdef"
+'''
+
+
+def monarch(date, name, tag, adm, dip, mil):
+    return f'''\t\t{date}={{
+\t\t\tmonarch={{
+\t\t\t\tname="{name}"
+\t\t\t\tcountry="{tag}"
+\t\t\t\tDIP={dip}
+\t\t\t\tADM={adm}
+\t\t\t\tMIL={mil}
+\t\t\t\tbirth_date=1420.1.1
+\t\t\t}}
+\t\t}}
+'''
+
+
+def country(tag, dev, cities, extra='', history='', human=False):
+    human_line = '\t\thuman=yes\n' if human else ''
+    return f'''\t{tag}={{
+{human_line}\t\tgovernment_rank=1
+\t\tgovernment_name="feudal_monarchy"
+\t\ttechnology={{
+\t\t\tadm_tech=4
+\t\t\tdip_tech=4
+\t\t\tmil_tech=5
+\t\t}}
+\t\tcapital=1
+\t\tdevelopment={dev - 1.5:.3f}
+\t\traw_development={dev:.3f}
+\t\tgreat_power_score={dev * 4:.3f}
+\t\tprimary_culture="lorentish"
+\t\treligion="regent_court"
+\t\treligious_unity=1.000
+\t\ttreasury=120.500
+\t\tmanpower=12.500
+\t\tmax_manpower=15.000
+\t\tarmy_tradition=25.000
+\t\tnavy_tradition=5.000
+\t\tarmy_professionalism=0.050
+\t\tinnovativeness=3.500
+\t\tabsolutism=20.000
+\t\tnum_of_cities={cities}
+\t\tpowers={{ 120 80 60 }}
+\t\tadm_spent_indexed={{
+\t\t\t0=400
+\t\t\t1=600
+\t\t\t7=50
+\t\t}}
+\t\tdip_spent_indexed={{
+\t\t\t1=600
+\t\t\t7=25
+\t\t\t14=30
+\t\t}}
+\t\tmil_spent_indexed={{
+\t\t\t1=600
+\t\t\t7=25
+\t\t}}
+\t\tactive_idea_groups={{
+\t\t\tdefensive_ideas=3
+\t\t}}
+\t\tactive_policy={{
+\t\t\tpolicy="the_combination_act"
+\t\t\tdate=1448.1.1
+\t\t}}
+\t\tledger={{
+\t\t\tlastmonthincometable={{ 4.500 3.000 2.000 0.000 0.000 0.000 0.000 0.000 0.000 0.000 0.000 0.000 0.000 0.000 0.000 0.000 0.000 0.000 }}
+\t\t}}
+\t\testate={{
+\t\t\ttype="estate_nobles"
+\t\t\tloyalty=45.000
+\t\t\tterritory=20.000
+\t\t\tinfluence_modifier={{
+\t\t\t\tvalue=10.000
+\t\t\t\tdesc="EST_VAL_EXAMPLE"
+\t\t\t\tdate=1460.1.1
+\t\t\t}}
+\t\t}}
+\t\tgovernment={{
+\t\t\tgovernment=monarchy
+\t\t\treform_stack={{
+\t\t\t\treforms={{ "monarchy_mechanic" "feudalism_reform" }}
+\t\t\t}}
+\t\t}}
+\t\tlosses={{
+\t\t\tmembers={{ 1200 800 0 300 150 0 0 50 0 0 0 0 1 0 0 0 0 0 0 0 0 }}
+\t\t}}
+{extra}\t\thistory={{
+{history}\t\t}}
+\t}}
+'''
+
+
+def province(pid, name, owner, buildings):
+    building_lines = ''.join(f'\t\t\t{b}=yes\n' for b in buildings)
+    return f'''\t-{pid}={{
+\t\tname="{name}"
+\t\towner="{owner}"
+\t\tcontroller="{owner}"
+\t\tcores={{ "{owner}" }}
+\t\tbase_tax=3.000
+\t\tbase_production=3.000
+\t\tbase_manpower=3.000
+\t\tbuildings={{
+{building_lines}\t\t}}
+\t}}
+'''
+
+
+def ledger(name, tags):
+    entries = ''.join(
+        f'\tledger={{\n\t\tname="{tag}"\n\t\tdata={{\n\t\t\t1445=8\n\t\t\t1448=10\n\t\t}}\n\t}}\n' for tag in tags
+    )
+    return f'{name}={{\n{entries}}}\n'
+
+
+def gamestate(players, countries, provinces, tags, diplomacy='', wars='', trade='', great_powers=()):
+    great_power_lines = ''.join(
+        f'\toriginal={{\n\t\tcountry="{tag}"\n\t\tvalue=100.000\n\t}}\n' for tag in great_powers
+    )
+    player_lines = ''.join(f'\t"{name}"\n\t"{tag}"\n' for name, tag in players)
+    statistics = ''.join(ledger(n, tags) for n in [
+        'income_statistics', 'nation_size_statistics', 'score_statistics', 'inflation_statistics'
+    ])
+    return f'''speed=2
+multiplayer_random_seed=10
+multiplayer_random_count=100
+current_age="age_of_discovery"
+players_countries={{
+{player_lines}}}
+start_date=1444.11.11
+trade={{
+{trade}}}
+{statistics}provinces={{
+{provinces}}}
+countries={{
+{countries}}}
+{wars}diplomacy={{
+{diplomacy}}}
+great_powers={{
+{great_power_lines}\tleaving={{
+\t}}
+}}
+'''
+
+
+TRADE = '''\tnode={
+\t\tdefinitions="lorent"
+\t\tcurrent=12.500
+\t\tlocal_value=6.000
+\t\ttotal=200.000
+\t\tmax=3.000
+\t\tA01={
+\t\t\tval=120.000
+\t\t\tmoney=2.500
+\t\t\thas_trader=yes
+\t\t\thas_capital=yes
+\t\t\ttype=0
+\t\t}
+\t\tB01={
+\t\t\tval=80.000
+\t\t\tmoney=1.000
+\t\t\thas_trader=yes
+\t\t\ttype=1
+\t\t}
+\t}
+'''
+
+WAR = '''previous_war={
+\tname="Lorentish-Gawedi War"
+\thistory={
+\t\t1446.1.1={
+\t\t\tadd_attacker="A01"
+\t\t\tadd_defender="B01"
+\t\t}
+\t\t1447.5.1={
+\t\t\tbattle={
+\t\t\t\tname="Battle of Lorentainé"
+\t\t\t\tlocation=1
+\t\t\t\tresult=yes
+\t\t\t\tattacker={
+\t\t\t\t\tcavalry=2000
+\t\t\t\t\tinfantry=8000
+\t\t\t\t\tlosses=900
+\t\t\t\t\tcountry="A01"
+\t\t\t\t\tcommander="Rogier"
+\t\t\t\t}
+\t\t\t\tdefender={
+\t\t\t\t\tinfantry=6000
+\t\t\t\t\tlosses=2100
+\t\t\t\t\tcountry="B01"
+\t\t\t\t\tcommander=""
+\t\t\t\t}
+\t\t\t}
+\t\t}
+\t\t1448.2.1={
+\t\t\trem_attacker="A01"
+\t\t\trem_defender="B01"
+\t\t}
+\t}
+\tparticipants={
+\t\tvalue=60.000
+\t\ttag="A01"
+\t\tlosses={
+\t\t\tmembers={ 900 300 0 0 0 0 0 0 0 0 0 0 0 0 0 0 0 0 0 0 0 }
+\t\t}
+\t}
+\tparticipants={
+\t\tvalue=40.000
+\t\ttag="B01"
+\t\tlosses={
+\t\t\tmembers={ 2100 500 0 0 0 0 0 0 0 0 0 0 0 0 0 0 0 0 0 0 0 }
+\t\t}
+\t}
+\toriginal_attacker="A01"
+\toriginal_defender="B01"
+\twar_goal={
+\t\ttype="take_claim"
+\t\tcasus_belli="cb_claim"
+\t}
+\tattacker_score=35.000
+}
+'''
+
+ALLIANCE = '''\talliance={
+\t\tfirst="A01"
+\t\tsecond="B01"
+\t\tstart_date=1449.1.1
+\t}
+'''
+
+RIVAL = '''\t\trival={
+\t\t\tcountry="B01"
+\t\t\tdate=1446.1.1
+\t\t}
+\t\tactive_relations={
+\t\t\tB01={
+\t\t\t\tcached_sum=-50.000
+\t\t\t}
+\t\t}
+'''
+
+MONARCHS_A01 = monarch('1444.11.11', 'Kalas', 'A01', 3, 2, 4) + monarch('1447.3.1', 'Lorenan', 'A01', 5, 3, 1)
+MONARCHS_B01 = monarch('1444.11.11', 'Gawe', 'B01', 2, 2, 2)
+
+
+def plaintext():
+    players = [('Alice', 'A01')]
+    tags = ['A01', 'B01']
+    countries = country('A01', 18.0, 2, RIVAL, MONARCHS_A01, human=True) + country('B01', 9.0, 1, '', MONARCHS_B01)
+    provinces = province(1, 'Lorentainé', 'A01', ['marketplace', 'temple']) \
+        + province(2, 'Rewanfork', 'A01', ['workshop']) \
+        + province(3, 'Gawed', 'B01', [])
+    return meta('1450.1.1', 'A01'), gamestate(players, countries, provinces, tags, ALLIANCE, WAR, TRADE, ['A01'])
+
+
+def anbennac_mp():
+    players = [('Alice', 'A01'), ('Bob', 'Z01')]
+    tags = ['A01', 'Z01', 'B01']
+    anbennac = '''\t\testate={
+\t\t\ttype="estate_mages"
+\t\t\tloyalty=55.000
+\t\t\tterritory=5.000
+\t\t}
+\t\tflags={
+\t\t\tanb_example_flag=1446.1.1
+\t\t\tis_monstrous_nation=1444.11.11
+\t\t}
+\t\tvariables={
+\t\t\tmagic_level=3.000
+\t\t\telven_tolerance=20.000
+\t\t\tracial_admin_used=2.000
+\t\t}
+\t\tmodifier={
+\t\t\tmodifier="anb_example_event_modifier"
+\t\t\tdate=1455.1.1
+\t\t}
+'''
+    countries = country('A01', 18.0, 2, RIVAL + anbennac, MONARCHS_A01, human=True) \
+        + country('Z01', 30.0, 3, anbennac, monarch('1444.11.11', 'Emperor', 'Z01', 4, 4, 4), human=True) \
+        + country('B01', 9.0, 1, '', MONARCHS_B01)
+    provinces = province(1, 'Lorentainé', 'A01', ['mage_tower', 'dwarven_road']) \
+        + province(2, 'Rewanfork', 'A01', ['fort_magic']) \
+        + province(3, 'Gawed', 'B01', []) \
+        + province(4, 'Anbenncóst', 'Z01', ['marketplace']) \
+        + province(5, 'Damesear', 'Z01', []) \
+        + province(6, 'Wesdam', 'Z01', [])
+    return meta('1450.1.1', 'A01', mods=['Anbennac'], multiplayer=True), \
+        gamestate(players, countries, provinces, tags, ALLIANCE, WAR, TRADE, ['Z01', 'A01'])
+
+
+def dynamic_tags():
+    players = [('Alice', 'A01')]
+    tags = ['A01', 'B01', 'C00', 'K00']
+    switched = '''\t\tprevious_country_tags={ "B02" }
+'''
+    tag_switch = MONARCHS_B01 + '''\t\t1448.1.1={
+\t\t\tchanged_tag_from="B02"
+\t\t}
+'''
+    countries = country('A01', 18.0, 2, '', MONARCHS_A01, human=True) \
+        + country('B01', 9.0, 1, switched, tag_switch) \
+        + country('C00', 6.0, 1, '', monarch('1448.1.1', 'Governor', 'C00', 2, 2, 2)) \
+        + country('K00', 6.0, 1, '', monarch('1448.1.1', 'Client', 'K00', 3, 1, 2))
+    provinces = province(1, 'Lorentainé', 'A01', []) \
+        + province(2, 'Rewanfork', 'A01', []) \
+        + province(3, 'Gawed', 'B01', []) \
+        + province(4, 'New Lorent', 'C00', []) \
+        + province(5, 'Client Hold', 'K00', [])
+    dependencies = '''\tdependency={
+\t\tfirst="A01"
+\t\tsecond="C00"
+\t\tstart_date=1448.1.1
+\t\tsubject_type="colony"
+\t}
+\tdependency={
+\t\tfirst="B01"
+\t\tsecond="K00"
+\t\tstart_date=1448.6.1
+\t\tsubject_type="client_vassal"
+\t}
+'''
+    return meta('1450.1.1', 'A01'), gamestate(players, countries, provinces, tags, ALLIANCE + dependencies)
+
+
+def write_plaintext(name, save):
+    with open(os.path.join(HERE, name), 'wb') as f:
+        f.write((HEADER + save[0] + save[1]).encode(ENCODING))
+
+
+def write_zipped(name, save):
+    with ZipFile(os.path.join(HERE, name), 'w', ZIP_DEFLATED) as z:
+        for entry, content in [('meta', save[0]), ('gamestate', save[1]), ('ai', '')]:
+            # A fixed timestamp keeps the archive identical between runs
+            info = ZipInfo(entry, date_time=(2024, 1, 1, 0, 0, 0))
+            info.compress_type = ZIP_DEFLATED
+            z.writestr(info, (HEADER + content).encode(ENCODING))
+
+
+# A tiny provinces.bmp and definition.csv for the map tests, province 5 being the sea:
+#
+#     1 1 2 2 3 3 5 5
+#     1 1 2 2 3 3 5 5
+#     4 4 4 4 3 3 5 5
+#     4 4 4 4 5 5 5 5
+MAP_ROWS = ['11223355', '11223355', '44443355', '44445555']
+MAP_COLORS = {
+    1: (128, 34, 64),
+    2: (0, 36, 128),
+    3: (128, 102, 0),
+    4: (64, 160, 32),
+    5: (5, 5, 240),
+}
+
+
+def write_map(name):
+    os.makedirs(os.path.join(HERE, name), exist_ok=True)
+    width, height = len(MAP_ROWS[0]), len(MAP_ROWS)
+    # 24 bit rows are padded to 4 bytes and stored bottom up, each pixel as blue, green, red
+    stride = (width * 3 + 3) & ~3
+    pixels = b''
+    for row in reversed(MAP_ROWS):
+        line = b''.join(bytes(reversed(MAP_COLORS[int(p)])) for p in row)
+        pixels += line + b'\0' * (stride - len(line))
+    header = struct.pack('<2sIHHI', b'BM', 54 + len(pixels), 0, 0, 54)
+    info = struct.pack('<IiiHHIIiiII', 40, width, height, 1, 24, 0, len(pixels), 2835, 2835, 0, 0)
+    with open(os.path.join(HERE, name, 'provinces.bmp'), 'wb') as f:
+        f.write(header + info + pixels)
+
+    lines = ['province;red;green;blue;x;x']
+    lines += [f'{i};{r};{g};{b};Province {i};x' for i, (r, g, b) in MAP_COLORS.items()]
+    with open(os.path.join(HERE, name, 'definition.csv'), 'wb') as f:
+        f.write(('\n'.join(lines) + '\n').encode(ENCODING))
+
+
+if __name__ == '__main__':
+    write_plaintext('plaintext.eu4', plaintext())
+    write_zipped('zipped.eu4', plaintext())
+    write_plaintext('anbennac_mp.eu4', anbennac_mp())
+    write_plaintext('dynamic_tags.eu4', dynamic_tags())
+    write_map('map')
@@ -0,0 +1,107 @@
+// Runs the parser over the saves in tests/fixtures and compares its parsed_country.json with the
+// golden files in tests/golden. After a change that is meant to alter the output, rewrite the
+// golden files with
+//
+//     UPDATE_GOLDEN=1 cargo test --test golden
+//
+// and review their diff before committing it. A missing golden file fails the test unless
+// UPDATE_GOLDEN is set, so a checkout without them can't pass by comparing nothing. The fixtures
+// themselves are made by tests/fixtures/generate.py.
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use serde_json::Value;
+
+const LOCALISATION: &str = "countries_l_english.yml";
+
+fn fixture(name: &str) -> PathBuf {
+    return Path::new(env!("CARGO_MANIFEST_DIR")).join("tests").join("fixtures").join(name);
+}
+
+fn golden(name: &str) -> PathBuf {
+    return Path::new(env!("CARGO_MANIFEST_DIR")).join("tests").join("golden").join(format!("{}.json", name));
+}
+
+fn work_dir(name: &str) -> PathBuf {
+    return Path::new(env!("CARGO_TARGET_TMPDIR")).join("golden").join(name);
+}
+
+// The parser writes parsed_country.json into its working directory, so every test gets its own
+fn parse_fixture(name: &str, save: &str, options: &[&str]) -> Value {
+    let work_dir = work_dir(name);
+    fs::create_dir_all(&work_dir).unwrap();
+    let json_path = work_dir.join("parsed_country.json");
+    let _ = fs::remove_file(&json_path);
+
+    let output = Command::new(env!("CARGO_BIN_EXE_rust"))
+        .current_dir(&work_dir)
+        .arg(fixture(LOCALISATION))
+        .arg(fixture(save))
+        .args(options)
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "Parsing {} failed:\n{}", save, String::from_utf8_lossy(&output.stderr));
+
+    let mut stats: Value = serde_json::from_str(&fs::read_to_string(&json_path).unwrap()).unwrap();
+    // The only value that changes from one run to the next
+    stats["meta"]["parsed_at"] = Value::from(0);
+    return stats;
+}
+
+fn check_golden(name: &str, stats: &Value) {
+    let golden_path = golden(name);
+    let pretty = serde_json::to_string_pretty(stats).unwrap() + "\n";
+    if env::var_os("UPDATE_GOLDEN").is_some() {
+        fs::create_dir_all(golden_path.parent().unwrap()).unwrap();
+        fs::write(&golden_path, pretty).unwrap();
+        eprintln!("Wrote {:?}", golden_path);
+        return;
+    }
+    assert!(golden_path.exists(), "Missing golden file {:?}, write it with UPDATE_GOLDEN=1 and review it before committing.", golden_path);
+
+    let expected: Value = serde_json::from_str(&fs::read_to_string(&golden_path).unwrap()).unwrap();
+    if *stats != expected {
+        let actual_path = work_dir(name).join(format!("{}.json", name));
+        fs::write(&actual_path, pretty).unwrap();
+        panic!(
+            "Output for {} differs from {:?}. It was written to {:?}, rerun with UPDATE_GOLDEN=1 if the change is intended.",
+            name, golden_path, actual_path
+        );
+    }
+}
+
+#[test]
+fn plaintext_save() {
+    let stats = parse_fixture("plaintext", "plaintext.eu4", &[]);
+    check_golden("plaintext", &stats);
+}
+
+#[test]
+fn zipped_save() {
+    let stats = parse_fixture("zipped", "zipped.eu4", &[]);
+    check_golden("zipped", &stats);
+}
+
+// Both fixtures hold the same game, only packed differently
+#[test]
+fn zipped_save_matches_plaintext() {
+    let mut plaintext = parse_fixture("zipped_plaintext", "plaintext.eu4", &[]);
+    let mut zipped = parse_fixture("zipped_zipped", "zipped.eu4", &[]);
+    plaintext["meta"]["file_name"] = Value::Null;
+    zipped["meta"]["file_name"] = Value::Null;
+    assert_eq!(plaintext, zipped);
+}
+
+#[test]
+fn anbennac_multiplayer_save() {
+    let stats = parse_fixture("anbennac_mp", "anbennac_mp.eu4", &["--players"]);
+    check_golden("anbennac_mp", &stats);
+}
+
+#[test]
+fn dynamic_tags_save() {
+    let stats = parse_fixture("dynamic_tags", "dynamic_tags.eu4", &[]);
+    check_golden("dynamic_tags", &stats);
+}
@@ -0,0 +1,72 @@
+// Runs the graph mode over tests/fixtures/dynamic_tags.eu4, where Lorent (A01, played by Alice)
+// is allied to Gawed (B01), and each has a subject: the colony C00 and the client state K00.
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use serde_json::{json, Value};
+
+fn fixture(name: &str) -> PathBuf {
+    return Path::new(env!("CARGO_MANIFEST_DIR")).join("tests").join("fixtures").join(name);
+}
+
+fn write_graph(output: &str, options: &[&str]) -> String {
+    let work_dir = Path::new(env!("CARGO_TARGET_TMPDIR")).join("graph");
+    fs::create_dir_all(&work_dir).unwrap();
+    let graph_path = work_dir.join(output);
+    let _ = fs::remove_file(&graph_path);
+
+    let result = Command::new(env!("CARGO_BIN_EXE_rust"))
+        .arg("graph")
+        .arg(fixture("countries_l_english.yml"))
+        .arg(fixture("dynamic_tags.eu4"))
+        .arg(&graph_path)
+        .args(options)
+        .output()
+        .unwrap();
+    assert!(result.status.success(), "Writing {} failed:\n{}", output, String::from_utf8_lossy(&result.stderr));
+    return fs::read_to_string(&graph_path).unwrap();
+}
+
+#[test]
+fn json_graph() {
+    let graph: Value = serde_json::from_str(&write_graph("diplomacy.json", &[])).unwrap();
+    assert_eq!(graph["nodes"], json!([
+        {"id": "A01", "name": "Lorent", "player": "Alice"},
+        {"id": "B01", "name": "Gawed", "player": null},
+        {"id": "C00", "name": "C00", "player": null},
+        {"id": "K00", "name": "K00", "player": null},
+    ]));
+    assert_eq!(graph["edges"], json!([
+        {"source": "A01", "target": "B01", "kind": "alliance", "directed": false},
+        {"source": "A01", "target": "C00", "kind": "subject", "directed": true},
+        {"source": "B01", "target": "K00", "kind": "subject", "directed": true},
+    ]));
+}
+
+#[test]
+fn dot_graph() {
+    let dot = write_graph("diplomacy.dot", &[]);
+    assert_eq!(dot, concat!(
+        "digraph diplomacy {\n",
+        "    \"A01\" [label=\"Lorent\\n(Alice)\", style=bold];\n",
+        "    \"B01\" [label=\"Gawed\"];\n",
+        "    \"C00\" [label=\"C00\"];\n",
+        "    \"K00\" [label=\"K00\"];\n",
+        "    \"A01\" -> \"B01\" [label=\"alliance\", color=blue, dir=none];\n",
+        "    \"A01\" -> \"C00\" [label=\"subject\", color=black];\n",
+        "    \"B01\" -> \"K00\" [label=\"subject\", color=black];\n",
+        "}\n",
+    ));
+}
+
+// Relations to countries left out of the selection aren't edges
+#[test]
+fn selected_graph() {
+    let graph: Value = serde_json::from_str(&write_graph("selected.json", &["--tags", "A01,C00"])).unwrap();
+    let nodes: Vec<&str> = graph["nodes"].as_array().unwrap().iter().map(|n| n["id"].as_str().unwrap()).collect();
+    assert_eq!(nodes, ["A01", "C00"]);
+    assert_eq!(graph["edges"], json!([
+        {"source": "A01", "target": "C00", "kind": "subject", "directed": true},
+    ]));
+}
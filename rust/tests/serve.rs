@@ -0,0 +1,138 @@
+// Starts `serve` on a free localhost port and talks to it over plain HTTP/1.1, uploading the
+// plaintext fixture save and reading its stats back.
+use std::io::{Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, Stdio};
+use std::thread;
+use std::time::Duration;
+
+use serde_json::Value;
+
+fn fixture(name: &str) -> PathBuf {
+    return Path::new(env!("CARGO_MANIFEST_DIR")).join("tests").join("fixtures").join(name);
+}
+
+// Kills the server when a test ends, passing or not
+struct Server {
+    child: Child,
+    address: String,
+}
+
+impl Drop for Server {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+fn start_server() -> Server {
+    // Binding port 0 picks a free port, which is released again for the server
+    let address = TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap().to_string();
+    let child = Command::new(env!("CARGO_BIN_EXE_rust"))
+        .arg("serve")
+        .arg(fixture("countries_l_english.yml"))
+        .arg(&address)
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .unwrap();
+    let server = Server { child, address };
+    for _ in 0..100 {
+        if TcpStream::connect(&server.address).is_ok() {
+            return server;
+        }
+        thread::sleep(Duration::from_millis(50));
+    }
+    panic!("Server did not start on {}", server.address);
+}
+
+// Sends one request and returns the status code and body of the response
+fn request(server: &Server, method: &str, path: &str, headers: &[(&str, &str)], body: &[u8]) -> (u16, Vec<u8>) {
+    let mut stream = TcpStream::connect(&server.address).unwrap();
+    let mut head = format!("{} {} HTTP/1.1\r\nHost: {}\r\nConnection: close\r\n", method, path, server.address);
+    if !headers.iter().any(|(field, _value)| field.eq_ignore_ascii_case("Content-Length")) {
+        head += &format!("Content-Length: {}\r\n", body.len());
+    }
+    for (field, value) in headers {
+        head += &format!("{}: {}\r\n", field, value);
+    }
+    head += "\r\n";
+    stream.write_all(head.as_bytes()).unwrap();
+    stream.write_all(body).unwrap();
+
+    let mut response = Vec::new();
+    let mut buffer = [0; 8192];
+    let (headers_end, content_length) = loop {
+        let read = stream.read(&mut buffer).unwrap();
+        assert!(read > 0, "Connection closed before the response headers");
+        response.extend_from_slice(&buffer[..read]);
+        let Some(end) = response.windows(4).position(|w| w == b"\r\n\r\n") else { continue };
+        let head = String::from_utf8_lossy(&response[..end]).to_string();
+        let content_length = head.lines()
+            .find_map(|l| l.split_once(':').filter(|(field, _value)| field.eq_ignore_ascii_case("Content-Length")))
+            .map(|(_field, value)| value.trim().parse::<usize>().unwrap())
+            .unwrap_or(0);
+        break (end + 4, content_length);
+    };
+    while response.len() < headers_end + content_length {
+        let read = stream.read(&mut buffer).unwrap();
+        assert!(read > 0, "Connection closed before the end of the response");
+        response.extend_from_slice(&buffer[..read]);
+    }
+
+    let status_line = String::from_utf8_lossy(&response[..headers_end]).lines().next().unwrap().to_string();
+    let status = status_line.split(' ').nth(1).unwrap().parse().unwrap();
+    return (status, response[headers_end..headers_end + content_length].to_vec());
+}
+
+fn json(body: &[u8]) -> Value {
+    return serde_json::from_slice(body).unwrap();
+}
+
+#[test]
+fn upload_then_read_stats() {
+    let server = start_server();
+    let (status, _body) = request(&server, "GET", "/stats", &[], b"");
+    assert_eq!(status, 404);
+
+    let save = std::fs::read(fixture("plaintext.eu4")).unwrap();
+    let (status, body) = request(&server, "POST", "/upload", &[("X-Save-Name", "plaintext.eu4")], &save);
+    assert_eq!(status, 200, "{}", String::from_utf8_lossy(&body));
+    let uploaded = json(&body);
+    assert_eq!(uploaded["meta"]["file_name"], "plaintext.eu4");
+
+    let (status, body) = request(&server, "GET", "/stats", &[], b"");
+    assert_eq!(status, 200);
+    assert_eq!(json(&body), uploaded);
+
+    let (status, body) = request(&server, "GET", "/stats/A01", &[], b"");
+    assert_eq!(status, 200);
+    let country = json(&body);
+    assert_eq!(country["tag"], "A01");
+    assert_eq!(country["player"], "Alice");
+
+    let (status, _body) = request(&server, "GET", "/stats/XXX", &[], b"");
+    assert_eq!(status, 404);
+
+    let (status, body) = request(&server, "GET", "/history", &[], b"");
+    assert_eq!(status, 200);
+    assert_eq!(json(&body).as_array().unwrap().len(), 1);
+}
+
+#[test]
+fn upload_with_invalid_save() {
+    let server = start_server();
+    let (status, _body) = request(&server, "POST", "/upload", &[], b"not a save");
+    assert_eq!(status, 422);
+    let (status, _body) = request(&server, "GET", "/stats", &[], b"");
+    assert_eq!(status, 404);
+}
+
+// Refused from the Content-Length alone, so the body is never sent
+#[test]
+fn upload_too_large() {
+    let server = start_server();
+    let (status, _body) = request(&server, "POST", "/upload", &[("Content-Length", "1073741824")], b"");
+    assert_eq!(status, 413);
+}
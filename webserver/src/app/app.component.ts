@@ -16,22 +16,258 @@ import * as stats from '../assets/parsed_country.json';
 import * as old_stats from '../assets/old_parsed_country.json';
 
 interface Eu4Stats {
+  schema_version: number;
+  meta: SaveMeta;
   countries: CountryStats[];
+  diplomacy: Diplomacy;
+  wars: War[];
+  trade: TradeNode[];
+  metrics?: MetricDefinition[];
+  ranking?: RankingConfig;
+  existing_tags?: string[];
+}
+
+interface RankingConfig {
+  categories: {
+    name: string;
+    weight: number;
+    inputs: { metric: string; weight: number }[];
+  }[];
+}
+
+interface Standing {
+  rank: number;
+  percentile: number;
+  player_rank: number | null;
+  player_percentile: number | null;
+}
+
+interface CountryRanking {
+  score: number;
+  rank: number;
+  categories: { category: string; score: number; rank: number }[];
+}
+
+interface MetricDefinition {
+  key: string;
+  category: string;
+  label: string;
+  unit: string;
+  path?: string | null;
+  lower_is_better?: boolean;
+}
+
+interface DiplomaticRelation {
+  kind: string;
+  first: string;
+  second: string;
+  start_date: string | null;
+}
+
+interface Opinion {
+  from: string;
+  to: string;
+  opinion: number;
+}
+
+interface Diplomacy {
+  relations: DiplomaticRelation[];
+  opinions: Opinion[];
+}
+
+interface UnitLosses {
+  infantry: number;
+  cavalry: number;
+  artillery: number;
+  heavy_ships: number;
+  light_ships: number;
+  galleys: number;
+  transports: number;
+}
+
+interface WarParticipant {
+  tag: string;
+  side: string;
+  participation: number;
+  losses: UnitLosses;
+}
+
+interface BattleSide {
+  country: string;
+  commander: string | null;
+  infantry: number;
+  cavalry: number;
+  artillery: number;
+  ships: number;
+  losses: number;
+}
+
+interface Battle {
+  name: string;
+  date: string;
+  location: string;
+  attacker_won: boolean;
+  attacker: BattleSide;
+  defender: BattleSide;
+}
+
+interface War {
+  name: string;
+  active: boolean;
+  start_date: string | null;
+  end_date: string | null;
+  casus_belli: string | null;
+  war_score: number | null;
+  attacker_leader: string;
+  defender_leader: string;
+  participants: WarParticipant[];
+  battles: Battle[];
+}
+
+interface TradeNodeCountry {
+  tag: string;
+  trade_power: number;
+  power_share: number;
+  income: number;
+  merchant: string | null;
+  home_node: boolean;
+}
+
+interface TradeNode {
+  name: string;
+  value: number;
+  local_value: number;
+  total_power: number;
+  countries: TradeNodeCountry[];
+}
+
+interface SaveMeta {
+  file_name: string;
+  parsed_at: number;
+  date: string;
+  game_version: string;
+  checksum: string;
+  dlc: string[];
+  mods: string[];
+  ironman: boolean;
+  multiplayer: boolean;
+  players: PlayerHistory[];
+}
+
+interface TagPeriod {
+  tag: string;
+  start: string | null;
+  end: string | null;
+}
+
+interface PlayerHistory {
+  name: string;
+  tag: string;
+  playing: boolean;
+  nation_tags: TagPeriod[];
 }
 
 interface CountryStats {
   tag: string;
   name: string;
   player: string | null;
+  previous_tags?: string[];
   country: Country;
   military: Military;
+  casualties?: Casualties;
   mana: Mana;
+  realm?: Realm;
+  subjects?: Subjects;
+  bloc?: Bloc;
+  trade?: CountryTrade;
+  anbennac?: Anbennac | null;
+  metrics?: { [key: string]: number };
+  ranking?: CountryRanking;
+  standings?: { [key: string]: Standing };
 } 
 
+interface SubjectRelation {
+  tag: string;
+  subject_type: string;
+  start_date: string | null;
+}
+
+interface Subjects {
+  overlord: string | null;
+  subject_type: string | null;
+  subjects: SubjectRelation[];
+}
+
+interface Bloc {
+  number_countries: number;
+  total_development: number;
+  income: number;
+  regiments: number;
+}
+
+interface Estate {
+  estate: string;
+  loyalty: number;
+  influence_modifiers: number;
+  territory: number;
+}
+
+interface Realm {
+  religion: string | null;
+  primary_culture: string | null;
+  accepted_cultures: string[];
+  religious_unity: number | null;
+  tolerance_own: number | null;
+  tolerance_heretic: number | null;
+  tolerance_heathen: number | null;
+  government: string | null;
+  government_reforms: string[];
+  government_rank: number;
+  estates: Estate[];
+  crown_land: number;
+  ruler?: Ruler | null;
+}
+
+interface Ruler {
+  name: string;
+  start_date: string;
+  adm: number;
+  dip: number;
+  mil: number;
+}
+
+interface Anbennac {
+  mage_estate: Estate | null;
+  magic: { [name: string]: number };
+  magic_flags: string[];
+  racial_tolerance: { [name: string]: number };
+  racial_administration: { [name: string]: number };
+  monstrous: boolean;
+  monstrous_flags: string[];
+}
+
+interface NodeTrade {
+  node: string;
+  trade_power: number;
+  power_share: number;
+  income: number;
+  merchant: string | null;
+}
+
+interface CountryTrade {
+  home_node: string | null;
+  trade_power: number;
+  trade_income: number;
+  merchants_steering: number;
+  merchants_collecting: number;
+  nodes: NodeTrade[];
+}
+
 interface Country {
   total_development: number;
   real_development: number;
   gp_score: number;
+  great_power?: boolean;
   powers_earned: number[];
   technology: number[];
   ideas: any[];
@@ -76,6 +312,27 @@ interface Military {
   naval_force_limit: number;
 }
 
+interface LargestBattle {
+  name: string;
+  date: string;
+  location: string;
+  size: number;
+}
+
+interface Casualties {
+  infantry_combat: number;
+  infantry_attrition: number;
+  cavalry_combat: number;
+  cavalry_attrition: number;
+  artillery_combat: number;
+  artillery_attrition: number;
+  total_land_losses: number;
+  ships_lost: number;
+  battles_won: number;
+  battles_lost: number;
+  largest_battle: LargestBattle | null;
+}
+
 interface Mana {
   mana_spent: number[];
   spent_developing: number[];
@@ -359,7 +616,7 @@ export class AppComponent implements AfterViewInit {
         name: i.name,
         player: i.player,
         country: i.country,
-        old_country: this.getOldCountry(old, i.tag)
+        old_country: this.getOldCountry(old, i)
       };
       output.push(d);
     });
@@ -367,14 +624,12 @@ export class AppComponent implements AfterViewInit {
     return output;
   }
 
-  getOldCountry(c: CountryStats[], tag: String) {
-    let country = null;
-    c.forEach((i) => {
-      if (i.tag == tag) {
-        country = i.country;
-      }
-    })
-    return country;
+  getOldCountry(c: CountryStats[], curr: CountryStats) {
+    // Players are followed by name so their numbers survive tag switches
+    const old = c.find((i) => curr.player != null && i.player == curr.player)
+      ?? c.find((i) => i.tag == curr.tag)
+      ?? c.find((i) => curr.previous_tags?.includes(i.tag));
+    return old ? old.country : null;
   }
 
   changePieData(c: CountryStats) {